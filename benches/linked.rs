@@ -0,0 +1,115 @@
+// Linked 与 ArenaLinked 性能对比
+// 不依赖第三方基准测试框架，每项操作重复若干次取最短耗时
+// 修改链表的操作先复制一份再执行，耗时包含复制
+// 用法：cargo bench --bench linked [-- 元素个数]
+
+use std::env;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use algorithm_data_structure::linked::{ArenaLinked, Linked};
+use rand::Rng;
+
+const ROUNDS: usize = 5;
+
+fn main() {
+    let n = env::args()
+        .skip(1)
+        .find_map(|a| a.parse::<usize>().ok())
+        .unwrap_or(200_000);
+    let mut rng = rand::thread_rng();
+    let data: Vec<u64> = (0..n).map(|_| rng.gen()).collect();
+    println!("{} elements, best of {} rounds", n, ROUNDS);
+    println!("{:<12} {:>14} {:>14}", "operation", "Linked", "ArenaLinked");
+    row(
+        "build",
+        || black_box(data.iter().cloned().collect::<Linked<u64>>()).len(),
+        || black_box(data.iter().cloned().collect::<ArenaLinked<u64>>()).len(),
+    );
+    let linked: Linked<u64> = data.iter().cloned().collect();
+    let arena: ArenaLinked<u64> = data.iter().cloned().collect();
+    row("iterate", || linked.iter().sum::<u64>() as usize, || arena.iter().sum::<u64>() as usize);
+    row(
+        "retain",
+        || {
+            let mut l = linked.clone();
+            l.retain(|x| x % 2 == 0);
+            l.len()
+        },
+        || {
+            let mut l = arena.clone();
+            l.retain(|x| x % 2 == 0);
+            l.len()
+        },
+    );
+    row(
+        "cursor",
+        || {
+            let mut l = linked.clone();
+            let mut c = l.cursor_mut();
+            while let Some(x) = c.current() {
+                let y = *x;
+                c.insert_after(y);
+                c.move_next();
+                c.move_next();
+            }
+            l.len()
+        },
+        || {
+            let mut l = arena.clone();
+            let mut c = l.cursor_mut();
+            while let Some(x) = c.current() {
+                let y = *x;
+                c.insert_after(y);
+                c.move_next();
+                c.move_next();
+            }
+            l.len()
+        },
+    );
+    row(
+        "sort",
+        || {
+            let mut l = linked.clone();
+            l.sort();
+            l.len()
+        },
+        || {
+            let mut l = arena.clone();
+            l.sort();
+            l.len()
+        },
+    );
+    row(
+        "reverse",
+        || {
+            let mut l = linked.clone();
+            l.reverse();
+            l.len()
+        },
+        || {
+            let mut l = arena.clone();
+            l.reverse();
+            l.len()
+        },
+    );
+}
+
+// 输出一行对比结果
+fn row<F, G>(name: &str, mut linked: F, mut arena: G) where F: FnMut() -> usize, G: FnMut() -> usize {
+    let a = best(&mut linked);
+    let b = best(&mut arena);
+    println!("{:<12} {:>14?} {:>14?}", name, a, b);
+}
+
+// 重复执行取最短耗时
+fn best<F>(f: &mut F) -> Duration where F: FnMut() -> usize {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
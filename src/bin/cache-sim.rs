@@ -0,0 +1,335 @@
+// 缓存访问序列模拟器
+// 读取访问序列（每行一个 key，或 CSV 格式 key,size），按不同容量回放到各种淘汰策略
+// 同时计算 Belady 最优替换（MIN）作为命中率上限，输出 CSV 或 Markdown 格式的命中率表格
+// 用法：cache-sim [--format csv|md] [--capacities 8,16,32] [TRACE]
+// 不指定 TRACE 时从标准输入读取，不指定容量时从 1 开始成倍增长到 key 的个数
+// 序列带 size 时容量按 size 之和计算，LFU 不支持权重输出 -
+// 不带 size 的 LRU 和 LFU 每次访问 O(1)，其余策略基于 Lru<List> 每次访问 O(容量)，
+// 长序列配合很大的容量会很慢（例如 20 万次访问、容量 1 万需要数秒），可以用 --capacities 限制容量
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process;
+use std::sync::Arc;
+
+use algorithm_data_structure::lfu::Lfu;
+use algorithm_data_structure::lru::{self, ArcPolicy, ClockPolicy, EvictionPolicy, FifoPolicy, List, Lru, LruMap, LruPolicy, MruPolicy, TwoQueuePolicy};
+
+// 输出格式
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Csv,
+    Markdown,
+}
+
+// 命令行参数
+struct Args {
+    format: Format,
+    capacities: Option<Vec<usize>>,
+    path: Option<String>,
+}
+
+// 访问序列 key 转换为编号 sizes[编号] 为该 key 的大小
+struct Trace {
+    keys: Vec<u32>,
+    sizes: Vec<usize>,
+    weighted: bool,
+}
+
+const POLICIES: [&str; 8] = ["LRU", "MRU", "FIFO", "CLOCK", "2Q", "ARC", "LFU", "MIN"];
+
+fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("cache-sim: {}", e);
+            eprintln!("usage: cache-sim [--format csv|md] [--capacities 8,16,32] [TRACE]");
+            eprintln!("note: policies other than LRU and LFU take O(capacity) per access; limit --capacities for long traces");
+            process::exit(2);
+        }
+    };
+    let trace = match args.path {
+        Some(ref p) => File::open(p).and_then(|f| read_trace(BufReader::new(f))),
+        None => read_trace(io::stdin().lock()),
+    };
+    let trace = match trace {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("cache-sim: {}", e);
+            process::exit(1);
+        }
+    };
+    let capacities = args.capacities.unwrap_or_else(|| default_capacities(&trace));
+    let rows: Vec<(usize, Vec<Option<f64>>)> = capacities.iter().map(|&n| (n, simulate(&trace, n))).collect();
+    let stdout = io::stdout();
+    if let Err(e) = print_table(&mut stdout.lock(), args.format, &rows) {
+        eprintln!("cache-sim: {}", e);
+        process::exit(1);
+    }
+}
+
+fn parse_args<I>(mut it: I) -> Result<Args, String> where I: Iterator<Item = String> {
+    let mut args = Args {
+        format: Format::Csv,
+        capacities: None,
+        path: None,
+    };
+    while let Some(a) = it.next() {
+        match a.as_str() {
+            "--format" => {
+                args.format = match it.next().as_deref() {
+                    Some("csv") => Format::Csv,
+                    Some("md") | Some("markdown") => Format::Markdown,
+                    other => return Err(format!("unknown format {:?}", other.unwrap_or(""))),
+                }
+            }
+            "--capacities" => {
+                let v = it.next().ok_or("missing value for --capacities")?;
+                let caps = v
+                    .split(',')
+                    .map(|c| c.trim().parse::<usize>().map_err(|_| format!("invalid capacity {:?}", c)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                args.capacities = Some(caps);
+            }
+            _ if a.starts_with("--") => return Err(format!("unknown option {}", a)),
+            _ => args.path = Some(a),
+        }
+    }
+    Ok(args)
+}
+
+// 读取访问序列 跳过空行和 # 开头的注释
+// 第一条记录的 size 是不含数字的单词（例如 key,size）时当作表头，其他无法解析的 size 都报错
+fn read_trace<R>(r: R) -> io::Result<Trace> where R: BufRead {
+    let mut ids: HashMap<String, u32> = HashMap::new();
+    let mut trace = Trace {
+        keys: Vec::new(),
+        sizes: Vec::new(),
+        weighted: false,
+    };
+    for (n, line) in r.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.splitn(2, ',');
+        let key = fields.next().unwrap_or("").trim();
+        let size = match fields.next().map(|s| s.trim().parse::<usize>()) {
+            Some(Ok(s)) => {
+                trace.weighted = true;
+                s
+            }
+            Some(Err(_)) if trace.keys.is_empty() && is_header(line) => continue,
+            Some(Err(_)) => {
+                let msg = format!("line {}: invalid size in {:?}", n + 1, line);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            }
+            None => 1,
+        };
+        let next = ids.len() as u32;
+        let id = *ids.entry(key.to_string()).or_insert(next);
+        if id as usize == trace.sizes.len() {
+            trace.sizes.push(size);
+        }
+        trace.keys.push(id);
+    }
+    Ok(trace)
+}
+
+// 表头的 size 列是单词 不含数字，避免把 a,1x 这样写错的记录当作表头丢掉
+fn is_header(line: &str) -> bool {
+    match line.split_once(',') {
+        Some((_, size)) => {
+            let size = size.trim();
+            !size.is_empty() && size.chars().all(|c| c.is_alphabetic() || c == '_' || c == ' ')
+        }
+        None => false,
+    }
+}
+
+// 默认容量 1, 2, 4 ... 直到 key 的个数（按 size 计算时为 size 之和）
+fn default_capacities(trace: &Trace) -> Vec<usize> {
+    let max = if trace.weighted {
+        trace.sizes.iter().sum()
+    } else {
+        trace.sizes.len()
+    };
+    let mut v = Vec::new();
+    let mut n = 1;
+    while n < max {
+        v.push(n);
+        n *= 2;
+    }
+    v.push(max.max(1));
+    v
+}
+
+// 按给定容量回放所有策略 返回各策略命中率
+fn simulate(trace: &Trace, n: usize) -> Vec<Option<f64>> {
+    let sizes = Arc::new(trace.sizes.clone());
+    let weigh = |k: &u32| sizes[*k as usize];
+    vec![
+        Some(if trace.weighted { replay::<LruPolicy>(trace, n, &sizes) } else { replay_lru(trace, n) }),
+        Some(replay::<MruPolicy>(trace, n, &sizes)),
+        Some(replay::<FifoPolicy>(trace, n, &sizes)),
+        Some(replay::<ClockPolicy>(trace, n, &sizes)),
+        Some(replay::<TwoQueuePolicy<u32>>(trace, n, &sizes)),
+        Some(replay::<ArcPolicy<u32>>(trace, n, &sizes)),
+        if trace.weighted { None } else { Some(replay_lfu(trace, n)) },
+        Some(lru::belady_min(&trace.keys, n, weigh).hit_ratio()),
+    ]
+}
+
+fn replay<P>(trace: &Trace, n: usize, sizes: &Arc<Vec<usize>>) -> f64 where P: EvictionPolicy<u32> {
+    let mut cache: Lru<List<u32>, u32, P> = if trace.weighted {
+        let sizes = Arc::clone(sizes);
+        Lru::with_weigher(n, move |k: &u32| sizes[*k as usize])
+    } else {
+        Lru::new(n)
+    };
+    for &k in trace.keys.iter() {
+        cache.lookup(k);
+    }
+    cache.stats().hit_ratio()
+}
+
+// 不带 size 时 LRU 用 LruMap 回放 每次访问 O(1)，结果与 Lru<List, LruPolicy> 相同
+fn replay_lru(trace: &Trace, n: usize) -> f64 {
+    let mut cache = LruMap::new(n);
+    let mut hits = 0;
+    for &k in trace.keys.iter() {
+        if cache.get(&k).is_some() {
+            hits += 1;
+        } else {
+            cache.put(k, ());
+        }
+    }
+    if trace.keys.is_empty() {
+        return 0.0;
+    }
+    hits as f64 / trace.keys.len() as f64
+}
+
+fn replay_lfu(trace: &Trace, n: usize) -> f64 {
+    let mut cache = Lfu::new(n);
+    let mut hits = 0;
+    for &k in trace.keys.iter() {
+        if cache.get(&k).is_some() {
+            hits += 1;
+        } else {
+            cache.put(k, ());
+        }
+    }
+    if trace.keys.is_empty() {
+        return 0.0;
+    }
+    hits as f64 / trace.keys.len() as f64
+}
+
+fn print_table<W>(w: &mut W, format: Format, rows: &[(usize, Vec<Option<f64>>)]) -> io::Result<()> where W: Write {
+    let cell = |r: &Option<f64>| match r {
+        Some(r) => format!("{:.4}", r),
+        None => "-".to_string(),
+    };
+    match format {
+        Format::Csv => {
+            writeln!(w, "capacity,{}", POLICIES.join(","))?;
+            for (n, ratios) in rows {
+                let cells: Vec<String> = ratios.iter().map(cell).collect();
+                writeln!(w, "{},{}", n, cells.join(","))?;
+            }
+        }
+        Format::Markdown => {
+            writeln!(w, "| capacity | {} |", POLICIES.join(" | "))?;
+            writeln!(w, "|---:|{}", "---:|".repeat(POLICIES.len()))?;
+            for (n, ratios) in rows {
+                let cells: Vec<String> = ratios.iter().map(cell).collect();
+                writeln!(w, "| {} | {} |", n, cells.join(" | "))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(v: &[&str]) -> Result<Args, String> {
+        parse_args(v.iter().map(|a| a.to_string()))
+    }
+
+    fn trace(s: &str) -> io::Result<Trace> {
+        read_trace(s.as_bytes())
+    }
+
+    // 命令行参数
+    #[test]
+    fn parse() {
+        let a = args(&[]).unwrap();
+        assert!(a.format == Format::Csv && a.capacities.is_none() && a.path.is_none());
+        let a = args(&["--format", "md", "--capacities", "8, 16,32", "trace.txt"]).unwrap();
+        assert!(a.format == Format::Markdown);
+        assert_eq!(Some(vec![8, 16, 32]), a.capacities);
+        assert_eq!(Some("trace.txt".to_string()), a.path);
+        assert!(args(&["--format", "markdown"]).unwrap().format == Format::Markdown);
+        assert_eq!(Some("unknown format \"xml\"".to_string()), args(&["--format", "xml"]).err());
+        assert_eq!(Some("unknown format \"\"".to_string()), args(&["--format"]).err());
+        assert_eq!(Some("missing value for --capacities".to_string()), args(&["--capacities"]).err());
+        assert_eq!(Some("invalid capacity \"x\"".to_string()), args(&["--capacities", "1,x"]).err());
+        assert_eq!(Some("unknown option --fast".to_string()), args(&["--fast"]).err());
+    }
+
+    // 访问序列 表头/注释/权重/错误的 size
+    #[test]
+    fn read() {
+        let t = trace("# comment\n\na\nb\n  a  \n#x\nc\n").unwrap();
+        assert_eq!(vec![0, 1, 0, 2], t.keys);
+        assert_eq!(vec![1, 1, 1], t.sizes);
+        assert!(!t.weighted);
+        // 表头被跳过 同一个 key 的 size 以第一次出现为准
+        let t = trace("key,size\na,10\nb, 20\na,30\n").unwrap();
+        assert_eq!(vec![0, 1, 0], t.keys);
+        assert_eq!(vec![10, 20], t.sizes);
+        assert!(t.weighted);
+        let t = trace("# header follows\nurl,bytes\nx,1\n").unwrap();
+        assert_eq!(vec![0], t.keys);
+        // 第一条记录写错不会被当作表头
+        let err = trace("a,1x\nb,2\n").err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert_eq!("line 1: invalid size in \"a,1x\"", err.to_string());
+        assert!(trace("a,\nb,2\n").is_err());
+        let err = trace("key,size\na,1\nb,size\n").err().unwrap();
+        assert_eq!("line 3: invalid size in \"b,size\"", err.to_string());
+        let t = trace("").unwrap();
+        assert!(t.keys.is_empty() && t.sizes.is_empty());
+    }
+
+    // 默认容量 成倍增长到 key 的个数或 size 之和
+    #[test]
+    fn capacities() {
+        let t = trace("a\nb\nc\nd\ne\n").unwrap();
+        assert_eq!(vec![1, 2, 4, 5], default_capacities(&t));
+        let t = trace("a\nb\nc\nd\n").unwrap();
+        assert_eq!(vec![1, 2, 4], default_capacities(&t));
+        let t = trace("a,3\nb,5\n").unwrap();
+        assert_eq!(vec![1, 2, 4, 8], default_capacities(&t));
+        assert_eq!(vec![1], default_capacities(&trace("").unwrap()));
+        assert_eq!(vec![1], default_capacities(&trace("a\n").unwrap()));
+    }
+
+    // LruMap 回放与 Lru<List, LruPolicy> 命中率一致
+    #[test]
+    fn lru_map_replay() {
+        let keys: String = (0..2000u32).map(|i| format!("{}\n", (i * 7919) % 97 % (i % 13 + 20))).collect();
+        let t = trace(&keys).unwrap();
+        let sizes = Arc::new(t.sizes.clone());
+        for n in [0, 1, 5, 16, 40, 200] {
+            assert_eq!(replay::<LruPolicy>(&t, n, &sizes), replay_lru(&t, n));
+        }
+        assert_eq!(0.0, replay_lru(&trace("").unwrap(), 4));
+    }
+}
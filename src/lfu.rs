@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// LFU 最不经常使用
+// 淘汰访问次数最少的元素，访问次数相同时淘汰最久未被访问的元素
+// 相比 LRU 热点数据不会因为一次大范围扫描被全部挤出
+// O(1) 实现：按访问次数分桶，桶之间按次数从小到大用双向链表串联
+// 每个桶内部是一条按访问时间排列的双向链表（head 最新 tail 最旧）
+// 查询：散列表定位节点，把节点移动到次数 + 1 的桶（不存在则在当前桶后面新建）
+// 淘汰：次数最少的桶（链表第一个桶）的 tail 节点
+// 老化：每 period 次访问把所有次数减半，长期不被访问的旧热点数据最终会被淘汰
+#[derive(Clone, Debug)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    bucket: usize,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+// 访问次数相同的节点
+#[derive(Clone, Debug)]
+struct Bucket {
+    freq: u64,
+    head: Option<usize>,
+    tail: Option<usize>,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Lfu<K, V> where K: Hash + Eq + Clone {
+    map: HashMap<K, usize>,
+    // 节点和桶都存放在 Vec 中通过下标互相指向，删除后的空位复用
+    nodes: Vec<Option<Node<K, V>>>,
+    free_nodes: Vec<usize>,
+    buckets: Vec<Option<Bucket>>,
+    free_buckets: Vec<usize>,
+    // 次数最少的桶
+    min: Option<usize>,
+    size: usize,
+    // 老化周期（访问次数）与当前计数
+    decay: Option<u64>,
+    ticks: u64,
+}
+
+impl<K, V> Lfu<K, V> where K: Hash + Eq + Clone {
+    // 根据容量初始化 不按容量预先分配（容量可以是 usize::MAX）
+    pub fn new(n: usize) -> Self {
+        Lfu {
+            map: HashMap::new(),
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            buckets: Vec::new(),
+            free_buckets: Vec::new(),
+            min: None,
+            size: n,
+            decay: None,
+            ticks: 0,
+        }
+    }
+    // 根据容量和老化周期初始化，周期不小于容量时老化的均摊成本为 O(1)
+    pub fn with_decay(n: usize, period: u64) -> Self {
+        let mut lfu = Lfu::new(n);
+        lfu.decay = Some(period.max(1));
+        lfu
+    }
+    // 获取长度
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    // 获取容量
+    pub fn capacity(&self) -> usize {
+        self.size
+    }
+    pub fn contains(&self, k: &K) -> bool {
+        self.map.contains_key(k)
+    }
+    // 查询访问次数
+    pub fn frequency(&self, k: &K) -> Option<u64> {
+        let i = *self.map.get(k)?;
+        let b = self.nodes[i].as_ref()?.bucket;
+        self.buckets[b].as_ref().map(|b| b.freq)
+    }
+    // 查询并增加访问次数
+    pub fn get(&mut self, k: &K) -> Option<&V> {
+        let i = *self.map.get(k)?;
+        self.touch(i);
+        self.nodes[i].as_ref().map(|n| &n.value)
+    }
+    // 查询可变引用并增加访问次数
+    pub fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        let i = *self.map.get(k)?;
+        self.touch(i);
+        self.nodes[i].as_mut().map(|n| &mut n.value)
+    }
+    // 查询但不增加访问次数
+    pub fn peek(&self, k: &K) -> Option<&V> {
+        let i = *self.map.get(k)?;
+        self.nodes[i].as_ref().map(|n| &n.value)
+    }
+    // 插入 key 已存在则替换 value 并增加访问次数，容量不足时淘汰并返回被淘汰的键值对
+    pub fn put(&mut self, k: K, v: V) -> Option<(K, V)> {
+        if let Some(&i) = self.map.get(&k) {
+            if let Some(n) = self.nodes[i].as_mut() {
+                n.value = v;
+            }
+            self.touch(i);
+            return None;
+        }
+        // 容量为 0 时无法缓存 直接返回
+        if self.size == 0 {
+            return Some((k, v));
+        }
+        let mut evicted = None;
+        if self.map.len() >= self.size {
+            evicted = self.pop_lfu();
+        }
+        // 新节点访问次数为 1 放入第一个桶
+        let b = match self.min {
+            Some(b) if self.buckets[b].as_ref().map(|b| b.freq) == Some(1) => b,
+            _ => self.new_bucket(None, 1),
+        };
+        let node = Node {
+            key: k.clone(),
+            value: v,
+            bucket: b,
+            prev: None,
+            next: None,
+        };
+        let i = match self.free_nodes.pop() {
+            Some(i) => {
+                self.nodes[i] = Some(node);
+                i
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.push_front(i, b);
+        self.map.insert(k, i);
+        self.tick();
+        evicted
+    }
+    // 淘汰访问次数最少且最久未访问的节点
+    pub fn pop_lfu(&mut self) -> Option<(K, V)> {
+        let b = self.min?;
+        let i = self.buckets[b].as_ref()?.tail?;
+        self.take(i)
+    }
+    // 根据 key 删除节点
+    pub fn remove(&mut self, k: &K) -> Option<V> {
+        let i = *self.map.get(k)?;
+        self.take(i).map(|(_, v)| v)
+    }
+    // 老化 所有访问次数减半（最小为 1），次数相同的相邻桶合并
+    // 合并时原次数较高的节点放在 head 一侧 优先保留
+    pub fn age(&mut self) {
+        let mut prev: Option<usize> = None;
+        let mut bucket = self.min;
+        while let Some(b) = bucket {
+            let (freq, next) = match self.buckets[b].as_mut() {
+                Some(x) => {
+                    x.freq = (x.freq / 2).max(1);
+                    (x.freq, x.next)
+                }
+                None => break,
+            };
+            match prev {
+                Some(p) if self.buckets[p].as_ref().map(|x| x.freq) == Some(freq) => {
+                    self.merge_into(b, p);
+                }
+                _ => prev = Some(b),
+            }
+            bucket = next;
+        }
+    }
+    // 按访问次数从少到多、同次数从旧到新返回所有 key（即淘汰顺序）
+    pub fn keys(&self) -> Vec<K> {
+        let mut v = Vec::with_capacity(self.len());
+        let mut bucket = self.min;
+        while let Some(b) = bucket.and_then(|b| self.buckets[b].as_ref()) {
+            let mut node = b.tail;
+            while let Some(n) = node.and_then(|i| self.nodes[i].as_ref()) {
+                v.push(n.key.clone());
+                node = n.prev;
+            }
+            bucket = b.next;
+        }
+        v
+    }
+    // 访问计数 达到老化周期时执行老化
+    fn tick(&mut self) {
+        if let Some(period) = self.decay {
+            self.ticks += 1;
+            if self.ticks >= period {
+                self.ticks = 0;
+                self.age();
+            }
+        }
+    }
+    // 节点移动到次数 + 1 的桶
+    fn touch(&mut self, i: usize) {
+        let b = match self.nodes[i].as_ref() {
+            Some(n) => n.bucket,
+            None => return,
+        };
+        let (freq, next) = match self.buckets[b].as_ref() {
+            Some(x) => (x.freq, x.next),
+            None => return,
+        };
+        let target = match next {
+            Some(n) if self.buckets[n].as_ref().map(|x| x.freq) == Some(freq + 1) => n,
+            _ => self.new_bucket(Some(b), freq + 1),
+        };
+        // 先建好目标桶再摘下节点 当前桶变空被删除时链表仍然连续
+        self.unlink(i);
+        self.push_front(i, target);
+        self.tick();
+    }
+    // 摘下节点并释放位置
+    fn take(&mut self, i: usize) -> Option<(K, V)> {
+        self.unlink(i);
+        let n = self.nodes[i].take()?;
+        self.map.remove(&n.key);
+        self.free_nodes.push(i);
+        Some((n.key, n.value))
+    }
+    // 把节点挂到桶的 head
+    fn push_front(&mut self, i: usize, b: usize) {
+        let head = self.buckets[b].as_ref().and_then(|x| x.head);
+        if let Some(n) = self.nodes[i].as_mut() {
+            n.bucket = b;
+            n.prev = None;
+            n.next = head;
+        }
+        if let Some(h) = head.and_then(|h| self.nodes[h].as_mut()) {
+            h.prev = Some(i);
+        }
+        if let Some(x) = self.buckets[b].as_mut() {
+            x.head = Some(i);
+            if x.tail.is_none() {
+                x.tail = Some(i);
+            }
+        }
+    }
+    // 把节点从桶中摘下，桶为空时删除桶
+    fn unlink(&mut self, i: usize) {
+        let (b, prev, next) = match self.nodes[i].as_mut() {
+            Some(n) => (n.bucket, n.prev.take(), n.next.take()),
+            None => return,
+        };
+        match prev.and_then(|p| self.nodes[p].as_mut()) {
+            Some(p) => p.next = next,
+            None => {
+                if let Some(x) = self.buckets[b].as_mut() {
+                    x.head = next;
+                }
+            }
+        }
+        match next.and_then(|n| self.nodes[n].as_mut()) {
+            Some(n) => n.prev = prev,
+            None => {
+                if let Some(x) = self.buckets[b].as_mut() {
+                    x.tail = prev;
+                }
+            }
+        }
+        if self.buckets[b].as_ref().map(|x| x.head.is_none()) == Some(true) {
+            self.remove_bucket(b);
+        }
+    }
+    // 在 prev 后面新建桶，prev 为 None 时作为第一个桶
+    fn new_bucket(&mut self, prev: Option<usize>, freq: u64) -> usize {
+        let next = match prev {
+            Some(p) => self.buckets[p].as_ref().and_then(|x| x.next),
+            None => self.min,
+        };
+        let bucket = Bucket {
+            freq,
+            head: None,
+            tail: None,
+            prev,
+            next,
+        };
+        let b = match self.free_buckets.pop() {
+            Some(b) => {
+                self.buckets[b] = Some(bucket);
+                b
+            }
+            None => {
+                self.buckets.push(Some(bucket));
+                self.buckets.len() - 1
+            }
+        };
+        match prev.and_then(|p| self.buckets[p].as_mut()) {
+            Some(p) => p.next = Some(b),
+            None => self.min = Some(b),
+        }
+        if let Some(n) = next.and_then(|n| self.buckets[n].as_mut()) {
+            n.prev = Some(b);
+        }
+        b
+    }
+    // 删除桶 prev <=> next 直接相连
+    fn remove_bucket(&mut self, b: usize) {
+        let (prev, next) = match self.buckets[b].take() {
+            Some(x) => (x.prev, x.next),
+            None => return,
+        };
+        match prev.and_then(|p| self.buckets[p].as_mut()) {
+            Some(p) => p.next = next,
+            None => self.min = next,
+        }
+        if let Some(n) = next.and_then(|n| self.buckets[n].as_mut()) {
+            n.prev = prev;
+        }
+        self.free_buckets.push(b);
+    }
+    // 把桶 b 的节点整体接到桶 p 的 head 一侧并删除桶 b
+    fn merge_into(&mut self, b: usize, p: usize) {
+        let (head, tail) = match self.buckets[b].as_ref() {
+            Some(x) => (x.head, x.tail),
+            None => return,
+        };
+        // 更新节点所属的桶
+        let mut node = head;
+        while let Some(n) = node.and_then(|i| self.nodes[i].as_mut()) {
+            n.bucket = p;
+            node = n.next;
+        }
+        let old_head = self.buckets[p].as_ref().and_then(|x| x.head);
+        if let Some(t) = tail.and_then(|t| self.nodes[t].as_mut()) {
+            t.next = old_head;
+        }
+        if let Some(h) = old_head.and_then(|h| self.nodes[h].as_mut()) {
+            h.prev = tail;
+        }
+        if let Some(x) = self.buckets[p].as_mut() {
+            if head.is_some() {
+                x.head = head;
+            }
+            if x.tail.is_none() {
+                x.tail = tail;
+            }
+        }
+        self.remove_bucket(b);
+    }
+}
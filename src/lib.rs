@@ -1,7 +1,8 @@
-mod lru;
-mod linked;
-mod stack;
-mod queue;
+pub mod lru;
+pub mod lfu;
+pub mod linked;
+pub mod stack;
+pub mod queue;
 mod recursion;
 mod sort;
 mod binary_search;
@@ -21,7 +22,7 @@ mod tests {
     use crate::linked::Linked;
     use crate::lru::Lru;
     use crate::queue::{ArrayQueue, Queue};
-    use super::{lru, linked};
+    use super::{lfu, lru, linked};
     use crate::{binary_tree, stack};
     use crate::stack::Stack;
     use crate::recursion;
@@ -86,6 +87,645 @@ mod tests {
         let v1 = vec![2, 4, 3, 1, 0];
         let v2: Vec<i32> = lru.cache().clone().into();
         assert_eq!(v1, v2);
+        // 查询结果与命中统计
+        let mut lru: Lru<Linked<_>, i32> = lru::Lru::new(2);
+        assert_eq!(lru::Outcome::Miss, lru.lookup(1));
+        assert_eq!(lru::Outcome::Miss, lru.lookup(2));
+        assert_eq!(lru::Outcome::Hit, lru.lookup(1));
+        assert_eq!(lru::Outcome::MissEvicted(2), lru.lookup(3));
+        assert_eq!(lru::Outcome::MissEvicted(1), lru.lookup(4));
+        let stats = lru.stats();
+        assert_eq!((1, 4, 2), (stats.hits, stats.misses, stats.evictions));
+        assert_eq!(5, stats.lookups());
+        assert!((stats.hit_ratio() - 0.2).abs() < 1e-9);
+        lru.reset_stats();
+        assert_eq!(&lru::CacheStats::default(), lru.stats());
+        assert_eq!(0.0, lru.stats().hit_ratio());
+    }
+
+    #[test]
+    // 可替换的淘汰策略 LRU/MRU/FIFO/CLOCK/2Q/ARC
+    fn lru_policy() {
+        use lru::{ArcPolicy, ClockPolicy, EvictionPolicy, FifoPolicy, MruPolicy, Outcome, TwoQueuePolicy};
+        // 同一组访问序列只需修改类型参数即可切换策略
+        fn run<P: EvictionPolicy<i32>>(size: usize, trace: &[i32]) -> (Vec<Outcome<i32>>, Vec<i32>) {
+            let mut lru: Lru<Linked<i32>, i32, P> = Lru::new(size);
+            let outcomes = trace.iter().map(|&x| lru.lookup(x)).collect();
+            (outcomes, lru.cache().clone().into())
+        }
+        // MRU 淘汰最近访问的元素
+        let (o, v) = run::<MruPolicy>(3, &[1, 2, 3, 4, 1, 5]);
+        assert_eq!(Outcome::MissEvicted(3), o[3]);
+        assert_eq!(Outcome::Hit, o[4]);
+        assert_eq!(Outcome::MissEvicted(1), o[5]);
+        assert_eq!(vec![5, 4, 2], v);
+        // FIFO 命中不影响淘汰顺序
+        let (o, v) = run::<FifoPolicy>(3, &[1, 2, 3, 1, 4]);
+        assert_eq!(Outcome::Hit, o[3]);
+        assert_eq!(Outcome::MissEvicted(1), o[4]);
+        assert_eq!(vec![4, 3, 2], v);
+        // CLOCK 被访问过的元素获得二次机会
+        let (o, v) = run::<ClockPolicy>(3, &[1, 2, 3, 1, 4]);
+        assert_eq!(Outcome::MissEvicted(2), o[4]);
+        assert_eq!(vec![4, 1, 3], v);
+        // 2Q 在 A1out 中再次访问的元素进入 Am 不会被扫描淘汰
+        let (o, v) = run::<TwoQueuePolicy<i32>>(4, &[1, 2, 3, 4, 5, 1, 6, 7, 1]);
+        assert_eq!(Outcome::MissEvicted(1), o[4]);
+        assert_eq!(Outcome::MissEvicted(2), o[5]);
+        assert_eq!(Outcome::Hit, o[8]);
+        assert_eq!(vec![1, 7, 6, 5], v);
+        let (o, v) = run::<TwoQueuePolicy<i32>>(4, &[1, 2, 3, 4, 5, 1, 6, 7, 4, 3, 8]);
+        assert_eq!(Outcome::MissEvicted(1), o[10]);
+        assert_eq!(vec![3, 4, 8, 7], v);
+        // ARC 根据幽灵队列命中调整 T1/T2 比例
+        let (o, v) = run::<ArcPolicy<i32>>(2, &[1, 1, 2, 3, 4, 3, 1]);
+        assert_eq!(Outcome::Hit, o[1]);
+        assert_eq!(Outcome::MissEvicted(2), o[3]);
+        assert_eq!(Outcome::MissEvicted(3), o[4]);
+        assert_eq!(Outcome::MissEvicted(1), o[5]);
+        assert_eq!(Outcome::MissEvicted(4), o[6]);
+        assert_eq!(vec![1, 3], v);
+        // 默认策略与原有 LRU 行为一致
+        let (o, v) = run::<lru::LruPolicy>(3, &[1, 2, 3, 1, 4]);
+        assert_eq!(Outcome::MissEvicted(2), o[4]);
+        assert_eq!(vec![4, 1, 3], v);
+        // 容量为 0 不缓存 所有策略都不插入也不淘汰
+        fn empty<P: EvictionPolicy<i32>>() {
+            let mut lru: Lru<Linked<i32>, i32, P> = Lru::new(0);
+            assert_eq!(Ok(Outcome::Miss), lru.try_lookup(1));
+            assert_eq!(Ok(Outcome::Miss), lru.try_lookup(1));
+            assert_eq!(0, lru.len());
+            assert_eq!(0, lru.stats().evictions);
+            assert!(lru.cache().is_empty());
+        }
+        empty::<ArcPolicy<i32>>();
+        empty::<lru::LruPolicy>();
+        empty::<MruPolicy>();
+        empty::<FifoPolicy>();
+        empty::<ClockPolicy>();
+        empty::<TwoQueuePolicy<i32>>();
+    }
+
+    #[test]
+    // 按权重计算容量的 LRU
+    fn lru_weighted() {
+        use lru::{LookupError, Outcome, WeightError};
+        let mut lru: Lru<List<String>, String> = Lru::with_weigher(10, |s: &String| s.len());
+        assert_eq!(Outcome::Miss, lru.lookup("aaaa".to_string()));
+        assert_eq!(Outcome::Miss, lru.lookup("bbb".to_string()));
+        assert_eq!(Outcome::Miss, lru.lookup("cc".to_string()));
+        assert_eq!(9, lru.weight());
+        assert_eq!(3, lru.len());
+        // 一次淘汰多个 tail 元素
+        let o = lru.lookup("dddddd".to_string());
+        assert_eq!(Outcome::MissEvictedMany(vec!["aaaa".to_string(), "bbb".to_string()]), o);
+        assert_eq!(8, lru.weight());
+        assert_eq!(Outcome::MissEvicted("cc".to_string()), lru.lookup("eeee".to_string()));
+        // 超过总容量的元素被拒绝 缓存不受影响
+        let heavy = "x".repeat(11);
+        let err = WeightError { element: heavy.clone(), weight: 11, capacity: 10 };
+        assert_eq!(Err(LookupError::Weight(err)), lru.try_lookup(heavy.clone()));
+        assert_eq!(Outcome::Miss, lru.lookup(heavy));
+        let v: Vec<String> = lru.cache().clone().into();
+        assert_eq!(vec!["eeee".to_string(), "dddddd".to_string()], v);
+        assert_eq!(Ok(Outcome::Hit), lru.try_lookup("dddddd".to_string()));
+        let stats = lru.stats();
+        assert_eq!((1, 7, 3), (stats.hits, stats.misses, stats.evictions));
+        // 其他策略同样按权重淘汰
+        fn check<P: lru::EvictionPolicy<i32>>() {
+            let mut lru: Lru<List<i32>, i32, P> = Lru::with_weigher(20, |x: &i32| (*x % 7) as usize);
+            for i in 0..200 {
+                lru.lookup((i * 37) % 23);
+                let v: Vec<i32> = lru.cache().clone().into();
+                assert_eq!(v.len(), lru.len());
+                assert_eq!(v.iter().map(|x| (*x % 7) as usize).sum::<usize>(), lru.weight());
+                assert!(lru.weight() <= 20);
+            }
+        }
+        check::<lru::LruPolicy>();
+        check::<lru::MruPolicy>();
+        check::<lru::FifoPolicy>();
+        check::<lru::ClockPolicy>();
+        check::<lru::TwoQueuePolicy<i32>>();
+        check::<lru::ArcPolicy<i32>>();
+        // 存储操作失败不会被当作未命中
+        struct Broken;
+        impl lru::EvictionPolicy<i32> for Broken {
+            fn new(_: usize) -> Self {
+                Broken
+            }
+            fn hit<C: Cache<i32>>(&mut self, cache: &mut C, i: usize) -> Result<(), lru::CacheError> {
+                cache.remove(cache.len() + i).map(|_| ())
+            }
+            fn miss<C: Cache<i32>>(&mut self, cache: &mut C, x: i32, _: bool) -> Result<Option<i32>, lru::CacheError> {
+                cache.insert(0, x).map(|_| None)
+            }
+            fn evict<C: Cache<i32>>(&mut self, _: &mut C) -> Result<Option<i32>, lru::CacheError> {
+                Ok(None)
+            }
+        }
+        let mut lru: Lru<List<i32>, i32, Broken> = Lru::new(2);
+        assert_eq!(Outcome::Miss, lru.lookup(1));
+        let err = lru::CacheError::OutOfBounds { index: 1, len: 1 };
+        assert_eq!(Err(LookupError::Cache(err)), lru.try_lookup(1));
+        assert_eq!((0, 1), (lru.stats().hits, lru.stats().misses));
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| lru.lookup(1)));
+        assert!(r.is_err());
+        assert_eq!((0, 1), (lru.stats().hits, lru.stats().misses));
+        // 权重之和溢出时按放不下处理 淘汰旧元素而不是 panic
+        let half = usize::MAX / 2 + 1;
+        let mut lru: Lru<List<i32>, i32> = Lru::with_weigher(usize::MAX, move |_: &i32| half);
+        assert_eq!(Ok(Outcome::Miss), lru.try_lookup(1));
+        assert_eq!(Ok(Outcome::MissEvicted(1)), lru.try_lookup(2));
+        assert_eq!((1, half), (lru.len(), lru.weight()));
+        // 不设置 weigher 每个元素权重为 1
+        let mut lru: Lru<List<i32>, i32> = Lru::new(2);
+        lru.lookup(1);
+        lru.lookup(2);
+        assert_eq!(Outcome::MissEvicted(1), lru.lookup(3));
+        assert_eq!(2, lru.weight());
+        assert_eq!(2, lru.capacity());
+    }
+
+    #[test]
+    // 存储结构越界返回错误 调整 LRU 容量
+    fn lru_resize() {
+        use lru::{ArcPolicy, CacheError, Outcome, TwoQueuePolicy};
+        // 越界和空存储返回 CacheError 不会 panic
+        let mut list: List<i32> = Cache::new();
+        assert_eq!(Err(CacheError::Empty), Cache::pop(&mut list));
+        assert_eq!(Err(CacheError::OutOfBounds { index: 0, len: 0 }), Cache::remove(&mut list, 0));
+        assert_eq!(Err(CacheError::OutOfBounds { index: 1, len: 0 }), Cache::insert(&mut list, 1, 1));
+        assert_eq!(Ok(()), Cache::insert(&mut list, 0, 1));
+        assert_eq!(Err(CacheError::OutOfBounds { index: 3, len: 1 }), Cache::index(&mut list, 3));
+        assert_eq!(Ok(&1), Cache::index(&mut list, 0));
+        let mut linked: Linked<i32> = Cache::new();
+        assert_eq!(Err(CacheError::Empty), Cache::pop(&mut linked));
+        assert_eq!(Err(CacheError::OutOfBounds { index: 2, len: 0 }), Cache::insert(&mut linked, 2, 1));
+        assert_eq!(Ok(()), Cache::insert(&mut linked, 0, 1));
+        assert_eq!(Ok(()), Cache::insert(&mut linked, 1, 2));
+        assert_eq!(Err(CacheError::OutOfBounds { index: 2, len: 2 }), Cache::remove(&mut linked, 2));
+        assert_eq!(2, Cache::len(&linked));
+        assert_eq!("index 2 out of bounds for length 2", CacheError::OutOfBounds { index: 2, len: 2 }.to_string());
+        // 缩小容量 按淘汰顺序返回被淘汰的元素
+        let mut lru: Lru<Linked<i32>, i32> = Lru::new(5);
+        for i in 0..5 {
+            lru.lookup(i);
+        }
+        lru.lookup(0);
+        assert_eq!(Ok(vec![1, 2]), lru.resize(3));
+        assert_eq!(3, lru.len());
+        assert_eq!(3, lru.capacity());
+        assert_eq!(2, lru.stats().evictions);
+        let v: Vec<i32> = lru.cache().clone().into();
+        assert_eq!(vec![0, 4, 3], v);
+        assert_eq!(Outcome::MissEvicted(3), lru.lookup(5));
+        // 扩大容量不淘汰
+        assert_eq!(Ok(vec![]), lru.resize(4));
+        assert_eq!(Outcome::Miss, lru.lookup(6));
+        assert_eq!(4, lru.len());
+        assert_eq!(Ok(vec![4, 0, 5, 6]), lru.resize(0));
+        assert!(lru.is_empty());
+        // 按权重缩小
+        let mut lru: Lru<List<i32>, i32> = Lru::with_weigher(10, |x: &i32| *x as usize);
+        for i in 1..5 {
+            lru.lookup(i);
+        }
+        assert_eq!(Ok(vec![1, 2]), lru.resize(7));
+        assert_eq!(7, lru.weight());
+        // 其他策略缩小后仍保持容量约束
+        let mut lru: Lru<List<i32>, i32, ArcPolicy<i32>> = Lru::new(8);
+        let mut two: Lru<List<i32>, i32, TwoQueuePolicy<i32>> = Lru::new(8);
+        for i in 0..100 {
+            lru.lookup((i * 7) % 13);
+            two.lookup((i * 7) % 13);
+            if i == 50 {
+                assert_eq!(Ok(5), lru.resize(3).map(|v| v.len()));
+                assert_eq!(Ok(5), two.resize(3).map(|v| v.len()));
+            }
+            assert!(lru.len() <= lru.capacity());
+            assert!(two.len() <= two.capacity());
+        }
+    }
+
+    #[test]
+    // LRU 快照与恢复
+    fn lru_snapshot() {
+        use lru::SnapshotError;
+        let mut lru: Lru<Linked<String>, String> = Lru::new(4);
+        for s in ["a", "b", "c", "a", "d"] {
+            lru.lookup(s.to_string());
+        }
+        let mut buf = Vec::new();
+        lru.snapshot(&mut buf).unwrap();
+        // 恢复后顺序和容量一致
+        let mut restored: Lru<Linked<String>, String> = Lru::restore(&buf[..]).unwrap();
+        assert_eq!(4, restored.capacity());
+        let v: Vec<String> = restored.cache().clone().into();
+        assert_eq!(vec!["d", "a", "c", "b"], v);
+        assert_eq!(0, restored.stats().lookups());
+        assert_eq!(lru::Outcome::MissEvicted("b".to_string()), restored.lookup("e".to_string()));
+        // 不同存储结构之间可以互相恢复
+        type Pairs = Lru<List<(u32, i64)>, (u32, i64)>;
+        let mut lru: Pairs = Lru::new(3);
+        lru.lookup((1, -1));
+        lru.lookup((2, -2));
+        let mut buf = Vec::new();
+        lru.snapshot(&mut buf).unwrap();
+        let restored: Lru<Linked<(u32, i64)>, (u32, i64)> = Lru::restore(&buf[..]).unwrap();
+        let v: Vec<(u32, i64)> = restored.cache().clone().into();
+        assert_eq!(vec![(2, -2), (1, -1)], v);
+        // 截断的快照
+        for n in 0..buf.len() {
+            let r = Pairs::restore(&buf[..n]);
+            assert!(matches!(r, Err(SnapshotError::Truncated)), "{} {:?}", n, r.err());
+        }
+        // 损坏的快照
+        let restore = |b: &[u8]| Lru::<List<String>, String>::restore(b).err().unwrap();
+        assert!(matches!(restore(b"LRUX\x02"), SnapshotError::BadMagic));
+        assert!(matches!(restore(b"LRUS\x01"), SnapshotError::UnsupportedVersion(1)));
+        assert!(matches!(restore(b"LRUS\x02\x02"), SnapshotError::Corrupt(_)));
+        let header = |size: u64, len: u64| {
+            let mut b = b"LRUS\x02\x00".to_vec();
+            b.extend_from_slice(&size.to_le_bytes());
+            b.extend_from_slice(&len.to_le_bytes());
+            b
+        };
+        assert!(matches!(restore(&header(1, 2)), SnapshotError::Corrupt(_)));
+        let mut b = header(2, 2);
+        b.extend_from_slice(&1u64.to_le_bytes());
+        b.push(b'a');
+        let mut dup = b.clone();
+        dup.extend_from_slice(&1u64.to_le_bytes());
+        dup.push(b'a');
+        assert!(matches!(restore(&dup), SnapshotError::Corrupt(_)));
+        b.extend_from_slice(&1u64.to_le_bytes());
+        b.push(0xff);
+        assert!(matches!(restore(&b), SnapshotError::Corrupt(_)));
+        // 错误长度不会申请超大内存
+        let mut b = header(1, 1);
+        b.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(restore(&b), SnapshotError::Truncated));
+        // 伪造的超大容量不会按容量预先分配 所有策略都能正常恢复
+        fn forged<P: lru::EvictionPolicy<u32>>(b: &[u8]) {
+            let mut lru: Lru<List<u32>, u32, P> = Lru::restore(b).unwrap();
+            let v: Vec<u32> = lru.cache().clone().into();
+            assert_eq!(vec![1], v);
+            for x in 0..100 {
+                lru.lookup(x);
+            }
+            assert_eq!(100, lru.len());
+        }
+        let mut b = header(u64::MAX, 1);
+        b.extend_from_slice(&1u32.to_le_bytes());
+        forged::<lru::LruPolicy>(&b);
+        forged::<lru::ClockPolicy>(&b);
+        forged::<lru::TwoQueuePolicy<u32>>(&b);
+        forged::<lru::ArcPolicy<u32>>(&b);
+        assert!(matches!(Lru::<List<u32>, u32>::restore(&header(u64::MAX, u64::MAX)[..]), Err(SnapshotError::Truncated)));
+        // 带权重的缓存 恢复时需要提供权重计算函数
+        let mut lru: Lru<List<String>, String> = Lru::with_weigher(6, |s: &String| s.len());
+        for s in ["aa", "bbb", "c", "dd"] {
+            lru.lookup(s.to_string());
+        }
+        let mut buf = Vec::new();
+        lru.snapshot(&mut buf).unwrap();
+        let err = Lru::<List<String>, String>::restore(&buf[..]).err().unwrap();
+        assert!(matches!(err, SnapshotError::WeigherMismatch { weighted: true }));
+        assert_eq!("snapshot of a weighted cache requires a weigher", err.to_string());
+        let restored: Lru<List<String>, String> = Lru::restore_with_weigher(&buf[..], |s: &String| s.len()).unwrap();
+        assert_eq!((6, 6, 3), (restored.capacity(), restored.weight(), restored.len()));
+        let v: Vec<String> = restored.cache().clone().into();
+        assert_eq!(vec!["dd", "c", "bbb"], v);
+        // 权重计算函数和写入时不同 元素放不下
+        let r = Lru::<List<String>, String>::restore_with_weigher(&buf[..], |s: &String| s.len() * 2);
+        assert!(matches!(r, Err(SnapshotError::Corrupt(_))));
+        // 不带权重的快照不能用权重恢复
+        let r = Lru::<List<String>, String>::restore_with_weigher(&header(2, 0)[..], |s: &String| s.len());
+        assert!(matches!(r, Err(SnapshotError::WeigherMismatch { weighted: false })));
+        assert_eq!("snapshot is truncated", SnapshotError::Truncated.to_string());
+    }
+
+    #[test]
+    // Belady 最优替换 命中率不低于任何在线策略
+    fn lru_belady() {
+        use lru::{FifoPolicy, LruPolicy};
+        let trace = [7, 0, 1, 2, 0, 3, 0, 4, 2, 3, 0, 3, 2, 1, 2, 0, 1, 7, 0, 1];
+        // 3 个页框 LRU 缺页 12 次 FIFO 缺页 15 次 OPT 缺页 9 次（允许旁路时 8 次）
+        let mut l: Lru<List<i32>, i32, LruPolicy> = Lru::new(3);
+        let mut f: Lru<List<i32>, i32, FifoPolicy> = Lru::new(3);
+        for &x in trace.iter() {
+            l.lookup(x);
+            f.lookup(x);
+        }
+        assert_eq!(12, l.stats().misses);
+        assert_eq!(15, f.stats().misses);
+        let min = lru::belady_min(&trace, 3, |_| 1);
+        assert_eq!(8, min.misses);
+        assert_eq!(20, min.lookups());
+        // 不再访问的元素旁路不缓存
+        let min = lru::belady_min(&[1, 2, 3, 1, 2, 3], 2, |_| 1);
+        assert_eq!((2, 4), (min.hits, min.misses));
+        // 按权重淘汰 超过容量的元素不缓存
+        let min = lru::belady_min(&["a", "bb", "a", "cccc", "bb", "cccc", "a"], 3, |s: &&str| s.len());
+        assert_eq!((3, 4), (min.hits, min.misses));
+        let min = lru::belady_min(&[1, 1], 0, |_| 1);
+        assert_eq!(0, min.hits);
+    }
+
+    #[test]
+    // 散列表 + 双向链表实现 O(1) LRU
+    fn lru_map() {
+        let mut m = lru::LruMap::new(3);
+        assert_eq!(None, m.put(1, "a"));
+        assert_eq!(None, m.put(2, "b"));
+        assert_eq!(None, m.put(3, "c"));
+        assert_eq!(vec![3, 2, 1], m.keys());
+        // 查询移动到 head
+        assert_eq!(Some(&"a"), m.get(&1));
+        assert_eq!(vec![1, 3, 2], m.keys());
+        // peek 不改变顺序
+        assert_eq!(Some(&"b"), m.peek(&2));
+        assert_eq!(vec![1, 3, 2], m.keys());
+        // 容量不足淘汰 tail
+        assert_eq!(Some((2, "b")), m.put(4, "d"));
+        assert_eq!(vec![4, 1, 3], m.keys());
+        // 替换 value 不淘汰
+        assert_eq!(None, m.put(3, "cc"));
+        assert_eq!(vec![3, 4, 1], m.keys());
+        if let Some(v) = m.get_mut(&1) {
+            *v = "aa";
+        }
+        assert_eq!(vec![1, 3, 4], m.keys());
+        assert_eq!(Some(&"aa"), m.peek(&1));
+        // 删除
+        assert_eq!(Some("cc"), m.remove(&3));
+        assert_eq!(None, m.remove(&3));
+        assert_eq!(vec![1, 4], m.keys());
+        assert_eq!(Some((4, "d")), m.pop_lru());
+        assert_eq!(Some((1, "aa")), m.pop_lru());
+        assert_eq!(None, m.pop_lru());
+        assert!(m.is_empty());
+        // 复用空闲位置
+        m.put(5, "e");
+        m.put(6, "f");
+        assert_eq!(vec![6, 5], m.keys());
+        assert_eq!(2, m.len());
+        // 容量为 0
+        let mut m = lru::LruMap::new(0);
+        assert_eq!(Some((1, 1)), m.put(1, 1));
+        assert_eq!(None, m.get(&1));
+        // 容量不限制 不会预先分配
+        let mut m = lru::LruMap::new(usize::MAX);
+        assert_eq!(None, m.put(1, 1));
+        assert_eq!(usize::MAX, m.capacity());
+    }
+
+    #[test]
+    // LRU 过期时间 使用手动时钟推进时间
+    fn lru_ttl() {
+        use std::time::Duration;
+        let clock = lru::ManualClock::new();
+        let mut m = lru::LruMap::with_clock(4, clock.clone());
+        m.set_default_ttl(Some(Duration::from_secs(10)));
+        m.put("a", 1);
+        m.put_with_ttl("b", 2, Duration::from_secs(5));
+        m.set_default_ttl(None);
+        m.put("c", 3);
+        assert_eq!(Some(Duration::from_secs(10)), m.ttl(&"a"));
+        assert_eq!(None, m.ttl(&"c"));
+        clock.advance(Duration::from_secs(5));
+        // 访问时惰性删除
+        assert!(!m.contains(&"b"));
+        assert_eq!(None, m.peek(&"b"));
+        assert_eq!(3, m.len());
+        assert_eq!(None, m.get(&"b"));
+        assert_eq!(2, m.len());
+        assert_eq!(Some(&1), m.get(&"a"));
+        assert_eq!(Some(Duration::from_secs(5)), m.ttl(&"a"));
+        // 重新插入刷新过期时间
+        m.put_with_ttl("c", 30, Duration::from_secs(1));
+        m.put_with_ttl("d", 4, Duration::from_secs(100));
+        clock.advance(Duration::from_secs(5));
+        // 主动清理 从旧到新返回
+        assert_eq!(vec![("a", 1), ("c", 30)], m.purge_expired());
+        assert_eq!(vec!["d"], m.keys());
+        assert!(m.purge_expired().is_empty());
+        clock.set(Duration::from_secs(1000));
+        assert_eq!(None, m.get_mut(&"d"));
+        assert!(m.is_empty());
+        // 过期后再次插入 按过期删除后重新插入而不是替换
+        use std::sync::{Arc, Mutex};
+        let causes = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&causes);
+        let mut m = lru::LruMap::with_clock(2, clock.clone());
+        m.add_listener(move |k: &&str, v: &i32, c| log.lock().unwrap().push((*k, *v, c)));
+        m.put_with_ttl("e", 5, Duration::from_secs(1));
+        m.put("f", 6);
+        m.put("f", 7);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(None, m.put("e", 50));
+        assert_eq!(vec!["e", "f"], m.keys());
+        assert_eq!(None, m.ttl(&"e"));
+        let expected = vec![("f", 6, lru::RemovalCause::Replaced), ("e", 5, lru::RemovalCause::Expired)];
+        assert_eq!(expected, *causes.lock().unwrap());
+        // 过期后 remove 返回 None，pop_lru 跳过过期节点
+        let mut m = lru::LruMap::with_clock(4, clock.clone());
+        m.put_with_ttl("g", 7, Duration::from_secs(1));
+        m.put_with_ttl("h", 8, Duration::from_secs(1));
+        m.put("i", 9);
+        m.put_with_ttl("j", 10, Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(None, m.remove(&"j"));
+        assert_eq!(Some(("i", 9)), m.pop_lru());
+        assert!(m.is_empty());
+        assert_eq!(None, m.pop_lru());
+        // 过期时间溢出视为永不过期，手动时钟饱和而不是截断
+        m.put_with_ttl("k", 11, Duration::MAX);
+        assert_eq!(None, m.ttl(&"k"));
+        clock.advance(Duration::MAX);
+        assert_eq!(Duration::from_nanos(u64::MAX), lru::Clock::now(&clock));
+        assert_eq!(Some(&11), m.get(&"k"));
+        clock.set(Duration::MAX);
+        assert_eq!(Duration::from_nanos(u64::MAX), lru::Clock::now(&clock));
+        // 系统时钟
+        let mut m = lru::LruMap::with_ttl(2, Duration::from_secs(60));
+        m.put(1, 1);
+        assert_eq!(Some(&1), m.get(&1));
+    }
+
+    #[test]
+    // 加载函数与移除监听器
+    fn lru_loader() {
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+        use lru::RemovalCause;
+        let clock = lru::ManualClock::new();
+        let mut m = lru::LruMap::with_clock(2, clock.clone());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&events);
+        m.add_listener(move |k: &&str, v: &i32, cause| log.lock().unwrap().push((*k, *v, cause)));
+        // 不存在时调用 loader 存在时直接返回
+        assert_eq!(1, m.get_or_insert_with("a", || 1));
+        assert_eq!(1, m.get_or_insert_with("a", || unreachable!()));
+        // loader 失败不插入
+        assert_eq!(Err("io"), m.get_or_try_insert_with("b", || Err("io")));
+        assert!(!m.contains(&"b"));
+        assert_eq!(Ok(2), m.get_or_try_insert_with("b", || Ok::<_, &str>(2)));
+        // 各种移除原因
+        m.put("a", 10);
+        m.put("c", 3);
+        assert_eq!(Some(10), m.remove(&"a"));
+        m.put_with_ttl("d", 4, Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(None, m.get(&"d"));
+        // 过期后重新加载
+        m.put_with_ttl("e", 5, Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(50, m.get_or_insert_with("e", || 50));
+        assert_eq!(Some(("c", 3)), m.pop_lru());
+        // 已过期的节点被 remove / pop_lru 清理时原因是过期而不是主动删除
+        m.put_with_ttl("f", 6, Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(None, m.remove(&"f"));
+        m.put_with_ttl("g", 7, Duration::from_secs(1));
+        assert_eq!(Some(&50), m.get(&"e"));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(Some(("e", 50)), m.pop_lru());
+        assert_eq!(vec![
+            ("a", 1, RemovalCause::Replaced),
+            ("b", 2, RemovalCause::Capacity),
+            ("a", 10, RemovalCause::Explicit),
+            ("d", 4, RemovalCause::Expired),
+            ("e", 5, RemovalCause::Expired),
+            ("c", 3, RemovalCause::Explicit),
+            ("f", 6, RemovalCause::Expired),
+            ("g", 7, RemovalCause::Expired),
+            ("e", 50, RemovalCause::Explicit),
+        ], *events.lock().unwrap());
+    }
+
+    #[test]
+    // 分片 LRU 多线程并发访问
+    fn lru_sharded() {
+        use std::sync::Arc;
+        use std::thread;
+        fn shareable<T: Send + Sync>(_: &T) {}
+        let c = Arc::new(lru::ShardedLru::new(64, 8));
+        shareable(&c);
+        assert_eq!(8, c.shards());
+        assert_eq!(64, c.capacity());
+        assert!(c.is_empty());
+        // 各分片容量之和等于总容量
+        for (n, shards) in [(10, 4), (0, 3), (7, 1), (100, 7)] {
+            assert_eq!(n, lru::ShardedLru::<u32, u32>::new(n, shards).capacity());
+        }
+        // 分片数多于容量时减少分片 每个分片都能缓存，插入后立即查询一定命中
+        for (n, shards) in [(2, 8), (3, 8), (0, 3)] {
+            let c = lru::ShardedLru::new(n, shards);
+            assert_eq!((shards.min(n).max(1), n), (c.shards(), c.capacity()));
+            for k in 0..100 {
+                c.put(k, k);
+                assert_eq!(if n == 0 { None } else { Some(k) }, c.get(&k));
+            }
+        }
+        // 多个线程同时读写
+        thread::scope(|s| {
+            for t in 0..8 {
+                let c = Arc::clone(&c);
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        let k = (t * 31 + i) % 100;
+                        if c.get(&k).is_none() {
+                            c.put(k, k * 2);
+                        }
+                    }
+                });
+            }
+        });
+        let stats = c.stats();
+        assert_eq!(8000, stats.lookups());
+        assert!(c.len() <= c.capacity());
+        assert!(stats.hits > 0 && stats.evictions > 0);
+        // 读到的值一定是写入的值
+        for k in 0..100 {
+            if let Some(v) = c.get(&k) {
+                assert_eq!(k * 2, v);
+            }
+        }
+        c.reset_stats();
+        assert_eq!(lru::CacheStats::default(), c.stats());
+        // 单线程行为与 LruMap 一致
+        let c = lru::ShardedLru::new(1, 1);
+        assert_eq!(None, c.put("a", 1));
+        assert_eq!(Some(("a", 1)), c.put("b", 2));
+        assert!(c.contains(&"b"));
+        assert_eq!(Some(2), c.remove(&"b"));
+        assert_eq!(None, c.get(&"b"));
+    }
+
+    #[test]
+    // 访问次数分桶实现 O(1) LFU
+    fn lfu() {
+        let mut c = lfu::Lfu::new(3);
+        c.put(1, "a");
+        c.put(2, "b");
+        c.put(3, "c");
+        assert_eq!(Some(&"a"), c.get(&1));
+        assert_eq!(Some(&"a"), c.get(&1));
+        assert_eq!(Some(&"b"), c.get(&2));
+        assert_eq!(Some(3), c.frequency(&1));
+        assert_eq!(Some(2), c.frequency(&2));
+        // 淘汰顺序 次数少的在前
+        assert_eq!(vec![3, 2, 1], c.keys());
+        assert_eq!(Some((3, "c")), c.put(4, "d"));
+        // 次数相同淘汰最久未访问的
+        assert_eq!(Some((4, "d")), c.put(5, "e"));
+        c.put(2, "bb");
+        assert_eq!(Some(3), c.frequency(&2));
+        assert_eq!(vec![5, 1, 2], c.keys());
+        assert_eq!(Some(&"bb"), c.peek(&2));
+        assert_eq!(Some(3), c.frequency(&2));
+        if let Some(v) = c.get_mut(&5) {
+            *v = "ee";
+        }
+        assert_eq!(Some("ee"), c.remove(&5));
+        c.get(&1);
+        assert_eq!(vec![2, 1], c.keys());
+        assert_eq!(2, c.len());
+        // 老化 次数减半 相同次数合并 原次数高的后淘汰
+        c.age();
+        assert_eq!(Some(2), c.frequency(&1));
+        assert_eq!(Some(1), c.frequency(&2));
+        c.age();
+        assert_eq!(Some(1), c.frequency(&1));
+        assert_eq!(vec![2, 1], c.keys());
+        assert_eq!(Some((2, "bb")), c.pop_lfu());
+        assert_eq!(Some((1, "a")), c.pop_lfu());
+        assert_eq!(None, c.pop_lfu());
+        assert!(c.is_empty());
+        // 不老化时旧热点一直保留，老化后最终被淘汰
+        let scan = |c: &mut lfu::Lfu<i32, i32>| {
+            c.put(1, 1);
+            for _ in 0..10 {
+                c.get(&1);
+            }
+            for k in 2..50 {
+                c.put(k, k);
+                c.get(&k);
+            }
+            c.contains(&1)
+        };
+        assert!(scan(&mut lfu::Lfu::new(2)));
+        assert!(!scan(&mut lfu::Lfu::with_decay(2, 4)));
+        // 容量为 0
+        let mut c = lfu::Lfu::new(0);
+        assert_eq!(Some((1, 1)), c.put(1, 1));
+        assert_eq!(None, c.get(&1));
+        // 容量不限制 不会预先分配
+        let mut c = lfu::Lfu::new(usize::MAX);
+        assert_eq!(None, c.put(1, 1));
+        assert_eq!(Some(&1), c.get(&1));
     }
 
     #[test]
@@ -121,13 +761,12 @@ mod tests {
         let mut v = Linked::from(vec![0, 1, 2, 3, 4, 5]);
         let mut v: Vec<_> = v.into();
         println!("{:?}", v);
-        // 删除倒数第 N 节点，这里通过长度查询（干查用双指针，快指针先走 n 快慢一起走 快到头 慢为 n - 1）
+        // 删除倒数第 N 节点（双指针，快指针先走 n 快慢一起走 快到头 慢为倒数第 n 个）
         let mut v = Linked::from(vec![0, 1, 2, 3, 4, 5]);
-        let len = v.len();
-        assert_eq!(Some(4), v.remove(len - 2));
-        // 获取中间节点，这里通过长度查询(干查用双指针，起点 head 快走 2 步 慢走 1 步，快到头 慢为中间节点)
-        let mut v = Linked::from(vec![1, 2, 3, 4, 5]);
-        assert_eq!(Some(&3), v.index(v.len() / 2));
+        assert_eq!(Some(4), v.remove_nth_from_end(2));
+        // 获取中间节点(双指针，起点 head 快走 2 步 慢走 1 步，快到头 慢为中间节点)
+        let v = Linked::from(vec![1, 2, 3, 4, 5]);
+        assert_eq!(Some(&3), v.middle());
         // 合并两个有序列表
         let v1 = Linked::from(vec![0, 1, 1, 3, 3, 5, 7, 9]);
         let v2 = Linked::from(vec![1, 2, 2, 4, 4, 6, 8, 10]);
@@ -151,6 +790,707 @@ mod tests {
         assert_eq!(false, v.contains(&0));
     }
 
+    // 链表迭代器与集合转换
+    #[test]
+    fn linked_iter() {
+        use linked::DoublyLinked;
+        let mut v: Linked<i32> = (1..=5).collect();
+        assert_eq!(5, v.len());
+        assert_eq!(vec![&1, &2, &3, &4, &5], v.iter().collect::<Vec<_>>());
+        assert_eq!(5, v.iter().len());
+        for x in v.iter_mut() {
+            *x *= 10;
+        }
+        for x in &mut v {
+            *x += 1;
+        }
+        assert_eq!(155, (&v).into_iter().sum::<i32>());
+        v.extend(vec![6, 7]);
+        assert_eq!(7, v.len());
+        assert_eq!(Some(&7), v.index(6));
+        let v1: Vec<i32> = v.into_iter().collect();
+        assert_eq!(vec![11, 21, 31, 41, 51, 6, 7], v1);
+        // 空链表
+        let mut v: Linked<i32> = Linked::new();
+        assert_eq!(None, v.iter().next());
+        v.extend(Some(1));
+        assert_eq!(Some(1), v.into_iter().next());
+        // Vec 转换为线性时间
+        let v1: Vec<u32> = (0..200_000).collect();
+        let l = Linked::from(v1.clone());
+        let v2: Vec<u32> = l.into();
+        assert_eq!(v1, v2);
+        // 双向链表支持反向迭代
+        let v: DoublyLinked<i32> = (1..=5).collect();
+        assert_eq!(vec![&5, &4, &3, &2, &1], v.iter().rev().collect::<Vec<_>>());
+        let mut it = v.iter();
+        assert_eq!(Some(&1), it.next());
+        assert_eq!(Some(&5), it.next_back());
+        assert_eq!(3, it.len());
+        assert_eq!(vec![&2, &3, &4], it.collect::<Vec<_>>());
+        let mut it = v.into_iter();
+        assert_eq!(Some(5), it.next_back());
+        assert_eq!(vec![1, 2, 3, 4], it.collect::<Vec<_>>());
+    }
+
+    // 长链表的查找、合并、复制和释放不会栈溢出
+    #[test]
+    fn linked_long() {
+        let n = 2_000_000;
+        let mut v: Linked<u32> = (0..n).collect();
+        assert!(v.contains(&(n - 1)));
+        assert!(!v.contains(&n));
+        assert_eq!(Some(&(n - 1)), v.index((n - 1) as usize));
+        v.push(n);
+        assert_eq!(Some(n), v.pop());
+        assert_eq!(Some(n - 1), v.pop());
+        assert_eq!(Some(n - 2), v.remove((n - 2) as usize));
+        let c = v.clone();
+        assert_eq!(c.len(), v.len());
+        drop(c);
+        // 合并两个百万级有序链表
+        let evens: Linked<u32> = (0..n).map(|x| x * 2).collect();
+        let odds: Linked<u32> = (0..n).map(|x| x * 2 + 1).collect();
+        let merged = Linked::merge_order(evens, odds).unwrap();
+        assert_eq!(2 * n as usize, merged.len());
+        assert!(merged.iter().zip(0..).all(|(x, i)| *x == i));
+        drop(merged);
+        assert_eq!("[1, 2]", format!("{:?}", Linked::from(vec![1, 2])));
+    }
+
+    // 链表经典算法 快慢指针/判环/回文/分组反转/旋转/去重/分区
+    #[test]
+    fn linked_algorithm() {
+        fn vec(l: &Linked<i32>) -> Vec<i32> {
+            l.iter().cloned().collect()
+        }
+        // 中间节点 偶数个取后一个
+        assert_eq!(Some(&3), Linked::from(vec![1, 2, 3, 4]).middle());
+        assert_eq!(Some(&1), Linked::from(vec![1]).middle());
+        assert_eq!(None, Linked::<i32>::new().middle());
+        // 删除倒数第 n 个
+        let mut v = Linked::from(vec![1, 2, 3]);
+        assert_eq!(Some(3), v.remove_nth_from_end(1));
+        assert_eq!(None, v.remove_nth_from_end(3));
+        assert_eq!(None, v.remove_nth_from_end(0));
+        assert_eq!(Some(1), v.remove_nth_from_end(2));
+        assert_eq!((vec![2], 1), (vec(&v), v.len()));
+        for len in 0..8 {
+            for n in 0..10 {
+                let mut v = Linked::from((0..len).collect::<Vec<i32>>());
+                let mut expect: Vec<i32> = (0..len).collect();
+                let removed = if n >= 1 && n <= expect.len() { Some(expect.remove(expect.len() - n)) } else { None };
+                assert_eq!(removed, v.remove_nth_from_end(n));
+                v.push(99);
+                expect.push(99);
+                assert_eq!((expect.clone(), expect.len()), (vec(&v), v.len()));
+            }
+        }
+        assert_eq!(None, Linked::from(vec![1, 2]).remove_nth_from_end(usize::MAX));
+        // Floyd 判环 后继数组 3 -> 2 -> 0 -> -4 -> 2 入口下标 1 环长 3
+        let succ = [1, 2, 3, 1];
+        assert_eq!(Some((1, 3)), linked::floyd(0, |i: usize| Some(succ[i])));
+        assert_eq!(None, linked::floyd(0, |i: usize| if i < 5 { Some(i + 1) } else { None }));
+        assert_eq!(Some((0, 1)), linked::floyd(7, |i: usize| Some(i)));
+        // 迭代函数 x => (x * x + 1) % 255 必然进入循环，和逐个记录出现位置的结果比较
+        let f = |x: u32| Some((x * x + 1) % 255);
+        for start in 0..255 {
+            let mut seen = HashMap::new();
+            let mut x = start;
+            let mut i = 0;
+            while !seen.contains_key(&x) {
+                seen.insert(x, i);
+                x = f(x).unwrap();
+                i += 1;
+            }
+            assert_eq!(Some((seen[&x], i - seen[&x])), linked::floyd(start, f));
+        }
+        // Box 链表不会成环
+        let v = Linked::from(vec![1, 1, 1]);
+        assert!(!v.has_cycle());
+        assert_eq!(None, v.cycle_start());
+        assert!(!Linked::<i32>::new().has_cycle());
+        // 回文 检查后链表不变
+        for (v1, expect) in [(vec![1, 2, 3, 2, 1], true), (vec![1, 2, 2, 1], true), (vec![1, 2], false), (vec![1], true), (vec![], true), (vec![1, 2, 3, 1], false)] {
+            let mut v = Linked::from(v1.clone());
+            assert_eq!(expect, v.is_palindrome());
+            assert_eq!(v1, vec(&v));
+            assert_eq!(v1.len(), v.len());
+        }
+        // 每 k 个一组反转
+        let mut v = Linked::from(vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        v.reverse_k_group(3);
+        assert_eq!(vec![3, 2, 1, 6, 5, 4, 7, 8], vec(&v));
+        v.reverse_k_group(8);
+        assert_eq!(vec![8, 7, 4, 5, 6, 1, 2, 3], vec(&v));
+        v.reverse_k_group(9);
+        v.reverse_k_group(1);
+        assert_eq!(vec![8, 7, 4, 5, 6, 1, 2, 3], vec(&v));
+        assert_eq!(8, v.len());
+        // 向右旋转
+        let mut v = Linked::from(vec![1, 2, 3, 4, 5]);
+        v.rotate(2);
+        assert_eq!(vec![4, 5, 1, 2, 3], vec(&v));
+        v.rotate(8);
+        assert_eq!(vec![1, 2, 3, 4, 5], vec(&v));
+        v.rotate(5);
+        assert_eq!(vec![1, 2, 3, 4, 5], vec(&v));
+        // 有序去重
+        let mut v = Linked::from(vec![1, 1, 2, 3, 3, 3, 4, 5, 5]);
+        v.dedup_sorted();
+        assert_eq!(vec![1, 2, 3, 4, 5], vec(&v));
+        assert_eq!(5, v.len());
+        // 分区 保持相对顺序
+        let mut v = Linked::from(vec![1, 4, 3, 2, 5, 2]);
+        assert_eq!(3, v.partition_around(&3));
+        assert_eq!(vec![1, 2, 2, 4, 3, 5], vec(&v));
+        assert_eq!(0, v.partition_around(&0));
+        assert_eq!(6, v.len());
+    }
+
+    // 链表归并排序与多路合并
+    #[test]
+    fn linked_sort() {
+        let mut v = Linked::from(vec![5, 1, 4, 2, 3, 1]);
+        v.sort();
+        let v1: Vec<i32> = v.into();
+        assert_eq!(vec![1, 1, 2, 3, 4, 5], v1);
+        let mut v: Linked<i32> = Linked::new();
+        v.sort();
+        assert!(v.is_empty());
+        // 稳定 按 key 排序时相同 key 保持原有顺序
+        let mut rng = rand::thread_rng();
+        let pairs: Vec<(u8, u32)> = (0..100_000).map(|i| (rng.gen_range(0..16), i)).collect();
+        let mut v = Linked::from(pairs.clone());
+        v.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut expect = pairs.clone();
+        expect.sort_by_key(|a| a.0);
+        assert_eq!(100_000, v.len());
+        let v1: Vec<_> = v.into();
+        assert_eq!(expect, v1);
+        // 降序
+        let mut v: Linked<i32> = (0..1000).collect();
+        v.sort_by(|a, b| b.cmp(a));
+        assert_eq!(Some(&999), v.index(0));
+        // 多路合并 相同元素先取靠前的链表
+        let lists = vec![
+            Linked::from(vec![(1, 'a'), (4, 'a'), (7, 'a')]),
+            Linked::new(),
+            Linked::from(vec![(1, 'a'), (2, 'c'), (9, 'c')]),
+            Linked::from(vec![(3, 'd')]),
+        ];
+        let v = Linked::merge_k(lists);
+        assert_eq!(7, v.len());
+        let v1: Vec<_> = v.into();
+        assert_eq!(vec![(1, 'a'), (1, 'a'), (2, 'c'), (3, 'd'), (4, 'a'), (7, 'a'), (9, 'c')], v1);
+        let lists: Vec<Linked<u32>> = (0..100).map(|k| (0..1000).map(|i| i * 100 + k).collect()).collect();
+        let v = Linked::merge_k(lists);
+        assert_eq!(100_000, v.len());
+        assert!(v.iter().zip(0..).all(|(x, i)| *x == i));
+        assert!(Linked::<u32>::merge_k(Vec::new()).is_empty());
+    }
+
+    // 链表拼接与拆分
+    #[test]
+    fn linked_split() {
+        fn vec(l: &Linked<i32>) -> Vec<i32> {
+            l.iter().cloned().collect()
+        }
+        // 拼接
+        let mut a = Linked::from(vec![1, 2]);
+        let mut b = Linked::from(vec![3, 4]);
+        a.append(&mut b);
+        assert_eq!((vec![1, 2, 3, 4], 4), (vec(&a), a.len()));
+        assert!(b.is_empty());
+        b.append(&mut a);
+        assert_eq!((4, 0), (b.len(), a.len()));
+        // 按下标拆分
+        let mut v: Linked<i32> = (0..6).collect();
+        let rest = v.split_off(4).unwrap();
+        assert_eq!((vec![0, 1, 2, 3], vec![4, 5]), (vec(&v), vec(&rest)));
+        assert_eq!((4, 2), (v.len(), rest.len()));
+        assert!(v.split_off(5).is_none());
+        assert!(v.split_off(4).unwrap().is_empty());
+        let all = v.split_off(0).unwrap();
+        assert_eq!((0, 4), (v.len(), all.len()));
+        // 按值拆分
+        let mut v: Linked<i32> = (0..6).collect();
+        let rest = v.split_at_value(&3).unwrap();
+        assert_eq!((vec![0, 1, 2], vec![3, 4, 5]), (vec(&v), vec(&rest)));
+        assert!(v.split_at_value(&9).is_none());
+        assert_eq!(3, v.len());
+        // 截断
+        let mut v: Linked<i32> = (0..6).collect();
+        v.truncate(10);
+        assert_eq!(6, v.len());
+        v.truncate(2);
+        assert_eq!((vec![0, 1], 2), (vec(&v), v.len()));
+        // 保留
+        let mut v: Linked<i32> = (0..10).collect();
+        v.retain(|x| x % 3 == 0);
+        assert_eq!((vec![0, 3, 6, 9], 4), (vec(&v), v.len()));
+        // 按范围删除
+        let mut v: Linked<i32> = (0..10).collect();
+        let d: Vec<i32> = v.drain(2..5).collect();
+        assert_eq!(vec![2, 3, 4], d);
+        assert_eq!((vec![0, 1, 5, 6, 7, 8, 9], 7), (vec(&v), v.len()));
+        let d: Vec<i32> = v.drain(5..).collect();
+        assert_eq!(vec![8, 9], d);
+        let d: Vec<i32> = v.drain(..=1).collect();
+        assert_eq!(vec![0, 1], d);
+        let d: Vec<i32> = v.drain(1..100).collect();
+        assert_eq!(vec![6, 7], d);
+        assert_eq!((vec![5], 1), (vec(&v), v.len()));
+        // 迭代时删除 提前结束时剩余节点保留
+        let mut v: Linked<i32> = (0..10).collect();
+        let odd: Vec<i32> = v.extract_if(|x| *x % 2 == 1).collect();
+        assert_eq!(vec![1, 3, 5, 7, 9], odd);
+        assert_eq!((vec![0, 2, 4, 6, 8], 5), (vec(&v), v.len()));
+        let first = v.extract_if(|x| *x > 2).next();
+        assert_eq!(Some(4), first);
+        assert_eq!((vec![0, 2, 6, 8], 4), (vec(&v), v.len()));
+        // 被删除的元素直接移出
+        let mut v: Linked<String> = ["a", "bb", "ccc"].iter().map(|s| s.to_string()).collect();
+        let long: Vec<String> = v.extract_if(|s| s.len() > 1).collect();
+        assert_eq!(vec!["bb".to_string(), "ccc".to_string()], long);
+    }
+
+    // 链表游标 遍历时原地修改
+    #[test]
+    fn linked_cursor() {
+        let mut v = Linked::from(vec![1, 2, 3, 4, 5, 6]);
+        // 删除偶数 奇数后面插入相反数
+        let mut c = v.cursor_mut();
+        while let Some(x) = c.current() {
+            if *x % 2 == 0 {
+                c.remove_current();
+            } else {
+                *x *= 10;
+                let y = -*x;
+                c.insert_after(y);
+                c.move_next();
+                c.move_next();
+            }
+        }
+        assert_eq!(6, c.index());
+        assert!(!c.move_next());
+        // 在末尾之后插入
+        c.insert_after(7);
+        assert_eq!(Some(&mut 7), c.current());
+        assert_eq!(7, v.len());
+        let v1: Vec<i32> = v.clone().into();
+        assert_eq!(vec![10, -10, 30, -30, 50, -50, 7], v1);
+        // 断开和拼接
+        let mut c = v.cursor_mut();
+        c.move_next();
+        assert_eq!(Some(&mut 30), c.peek_next());
+        let rest = c.split_after();
+        assert_eq!(5, rest.len());
+        assert_eq!(2, v.len());
+        let mut c = v.cursor_mut();
+        c.splice_after(rest);
+        c.splice_after(Linked::new());
+        assert_eq!(Some(&mut 10), c.current());
+        assert_eq!(7, v.len());
+        let v1: Vec<i32> = v.into();
+        assert_eq!(vec![10, 30, -30, 50, -50, 7, -10], v1);
+        // 空链表
+        let mut v: Linked<i32> = Linked::new();
+        let mut c = v.cursor_mut();
+        assert_eq!(None, c.current());
+        assert_eq!(None, c.remove_current());
+        assert_eq!(0, c.split_after().len());
+        c.splice_after(Linked::from(vec![1, 2]));
+        c.move_next();
+        c.move_next();
+        c.insert_after(3);
+        assert_eq!(3, v.len());
+        assert_eq!(Some(&3), v.index(2));
+    }
+
+    // 循环链表 约瑟夫问题/轮询调度
+    #[test]
+    fn circular_linked() {
+        use linked::{CircularLinked, RoundRobin};
+        let mut c: CircularLinked<i32> = CircularLinked::new();
+        assert_eq!(None, c.current());
+        assert_eq!(None, c.remove_current());
+        assert_eq!(None, c.advance(3));
+        c.push(1);
+        c.push(2);
+        c.push(3);
+        assert_eq!(Some(&1), c.current());
+        c.rotate();
+        assert_eq!(Some(&2), c.current());
+        assert_eq!(vec![&2, &3, &1], c.iter().collect::<Vec<_>>());
+        // 超过长度取余 转回起点
+        assert_eq!(Some(&mut 2), c.advance(6));
+        assert_eq!(Some(&mut 1), c.advance(2));
+        // 在当前节点前插入
+        c.push(4);
+        assert_eq!(vec![&1, &2, &3, &4], c.iter().collect::<Vec<_>>());
+        if let Some(x) = c.current_mut() {
+            *x = 10;
+        }
+        assert_eq!(Some(10), c.remove_current());
+        assert_eq!(Some(&2), c.current());
+        assert_eq!(Some(2), c.remove_current());
+        assert_eq!(Some(3), c.remove_current());
+        assert_eq!(Some(4), c.remove_current());
+        assert!(c.is_empty());
+        // 空位复用后仍能正常成环
+        let mut c = CircularLinked::from(vec![1]);
+        c.rotate();
+        assert_eq!(Some(&1), c.current());
+        c.push(2);
+        assert_eq!(vec![&1, &2], c.iter().collect::<Vec<_>>());
+        // 约瑟夫问题
+        assert_eq!(vec![3, 6, 2, 7, 5, 1, 4], linked::josephus(7, 3));
+        assert_eq!(vec![1, 2, 3], linked::josephus(3, 1));
+        assert_eq!(Some(&31), linked::josephus(41, 3).last());
+        assert!(linked::josephus(5, 0).is_empty());
+        assert!(linked::josephus(0, 2).is_empty());
+        // 轮询调度 任务按时间片轮流执行 结束的任务移出
+        let jobs = vec![vec!["a1", "a2", "a3"].into_iter(), vec!["b1"].into_iter(), vec!["c1", "c2"].into_iter()];
+        let mut rr: RoundRobin<_> = jobs.into_iter().collect();
+        assert_eq!(vec!["a1", "b1", "c1", "a2"], rr.by_ref().take(4).collect::<Vec<_>>());
+        rr.push(vec!["d1", "d2"].into_iter());
+        assert_eq!(vec!["c2", "a3", "d1", "d2"], rr.by_ref().collect::<Vec<_>>());
+        assert!(rr.is_empty());
+        assert_eq!(None, rr.next());
+    }
+
+    // arena 链表 与 Linked 接口和结果一致
+    #[test]
+    fn arena_linked() {
+        use linked::ArenaLinked;
+        fn vec<T: Clone>(l: &ArenaLinked<T>) -> Vec<T> {
+            l.iter().cloned().collect()
+        }
+        // 基本操作
+        let mut a: ArenaLinked<i32> = ArenaLinked::new();
+        let mut l: Linked<i32> = Linked::new();
+        for i in 0..10 {
+            a.push(i);
+            l.push(i);
+        }
+        assert_eq!(l.remove(3), a.remove(3));
+        assert_eq!(l.pop(), a.pop());
+        l.insert(0, 20);
+        a.insert(0, 20);
+        l.insert(9, 21);
+        a.insert(9, 21);
+        a.insert(99, 22);
+        assert_eq!(l.index(4).cloned(), a.index(4).cloned());
+        assert_eq!(None, a.index(10));
+        assert!(a.contains(&21));
+        l.reverse();
+        a.reverse();
+        let v: Vec<i32> = l.clone().into();
+        assert_eq!(v, vec(&a));
+        assert_eq!(l.len(), a.len());
+        // 空位复用后顺序仍正确
+        for _ in 0..5 {
+            a.remove(0);
+        }
+        a.extend(vec![30, 31, 32]);
+        a.push(33);
+        assert_eq!(Some(&33), a.index(a.len() - 1));
+        assert_eq!(vec![4, 2, 1, 0, 20, 30, 31, 32, 33], vec(&a));
+        for x in a.iter_mut() {
+            *x += 1;
+        }
+        for x in &mut a {
+            *x -= 1;
+        }
+        assert_eq!(vec![4, 2, 1, 0, 20, 30, 31, 32, 33], vec(&a));
+        // 可变迭代沿链表顺序，不受空位位置影响
+        let mut it = a.iter_mut();
+        assert_eq!(9, it.len());
+        *it.next().unwrap() = 40;
+        *it.nth(4).unwrap() = 50;
+        assert_eq!(3, it.len());
+        assert_eq!(vec![40, 2, 1, 0, 20, 50, 31, 32, 33], vec(&a));
+        assert_eq!(None, ArenaLinked::<i32>::new().iter_mut().next());
+        // try_push / try_insert 失败时交还元素
+        let mut b: ArenaLinked<i32> = (0..3).collect();
+        b.remove(1);
+        assert_eq!(Ok(()), b.try_push(3));
+        assert_eq!(Ok(()), b.try_insert(0, 4));
+        assert_eq!(Err(5), b.try_insert(5, 5));
+        assert_eq!(Err(lru::CacheError::OutOfBounds { index: 5, len: 4 }), Cache::insert(&mut b, 5, 5));
+        assert_eq!(Ok(()), Cache::insert(&mut b, 4, 5));
+        assert_eq!(vec![4, 0, 2, 3, 5], vec(&b));
+        // 空链表时超过长度的索引不插入，Linked 则插入为 head
+        let mut b: ArenaLinked<i32> = ArenaLinked::new();
+        let mut l: Linked<i32> = Linked::new();
+        b.insert(3, 1);
+        l.insert(3, 1);
+        assert_eq!((0, 1), (b.len(), l.len()));
+        assert_eq!("[1, 2]", format!("{:?}", ArenaLinked::from(vec![1, 2])));
+        // 算法
+        let a = ArenaLinked::from(vec![1, 2, 3, 4]);
+        assert_eq!(Some(&3), a.middle());
+        let mut a = ArenaLinked::from(vec![1, 2, 3]);
+        assert_eq!(None, a.remove_nth_from_end(4));
+        assert_eq!(Some(3), a.remove_nth_from_end(1));
+        a.push(4);
+        assert_eq!(Some(1), a.remove_nth_from_end(3));
+        assert_eq!(vec![2, 4], vec(&a));
+        for v1 in [vec![1, 2, 3, 2, 1], vec![1, 2, 2, 1], vec![1, 2], vec![1], vec![], vec![1, 2, 3, 1]] {
+            let mut a = ArenaLinked::from(v1.clone());
+            let mut l = Linked::from(v1.clone());
+            assert_eq!(l.is_palindrome(), a.is_palindrome());
+            assert_eq!(v1, vec(&a));
+        }
+        let mut a: ArenaLinked<i32> = (1..=8).collect();
+        a.reverse_k_group(3);
+        assert_eq!(vec![3, 2, 1, 6, 5, 4, 7, 8], vec(&a));
+        a.push(9);
+        a.rotate(2);
+        assert_eq!(vec![8, 9, 3, 2, 1, 6, 5, 4, 7], vec(&a));
+        a.push(10);
+        assert_eq!(Some(&10), a.index(9));
+        let mut a = ArenaLinked::from(vec![1, 1, 2, 3, 3, 3]);
+        a.dedup_sorted();
+        a.push(4);
+        assert_eq!((vec![1, 2, 3, 4], 4), (vec(&a), a.len()));
+        let mut a = ArenaLinked::from(vec![1, 4, 3, 2, 5, 2]);
+        assert_eq!(3, a.partition_around(&3));
+        a.push(0);
+        assert_eq!(vec![1, 2, 2, 4, 3, 5, 0], vec(&a));
+        // 排序与合并
+        let mut rng = rand::thread_rng();
+        let pairs: Vec<(u8, u32)> = (0..10_000).map(|i| (rng.gen_range(0..16), i)).collect();
+        let mut a = ArenaLinked::from(pairs.clone());
+        a.sort_by(|x, y| x.0.cmp(&y.0));
+        let mut expect = pairs.clone();
+        expect.sort_by_key(|x| x.0);
+        a.push((0, 0));
+        expect.push((0, 0));
+        assert_eq!(expect, vec(&a));
+        let mut a = ArenaLinked::from(vec![3, 1, 2]);
+        a.sort();
+        assert_eq!(vec![1, 2, 3], vec(&a));
+        let m = ArenaLinked::merge_order(ArenaLinked::from(vec![1, 3, 5]), ArenaLinked::from(vec![2, 4])).unwrap();
+        assert_eq!(vec![1, 2, 3, 4, 5], vec(&m));
+        assert!(ArenaLinked::<i32>::merge_order(ArenaLinked::new(), ArenaLinked::new()).is_none());
+        let m = ArenaLinked::merge_k(vec![ArenaLinked::from(vec![1, 4]), ArenaLinked::new(), ArenaLinked::from(vec![2, 3, 5])]);
+        assert_eq!((vec![1, 2, 3, 4, 5], 5), (vec(&m), m.len()));
+        // 拼接与拆分
+        let mut a: ArenaLinked<i32> = (0..4).collect();
+        let mut b: ArenaLinked<i32> = (4..6).collect();
+        a.append(&mut b);
+        assert!(b.is_empty());
+        let rest = a.split_off(4).unwrap();
+        assert_eq!((vec![0, 1, 2, 3], vec![4, 5]), (vec(&a), vec(&rest)));
+        assert!(a.split_off(5).is_none());
+        let rest = a.split_at_value(&2).unwrap();
+        assert_eq!((vec![0, 1], vec![2, 3]), (vec(&a), vec(&rest)));
+        a.push(2);
+        assert_eq!(vec![0, 1, 2], vec(&a));
+        a.truncate(1);
+        a.push(5);
+        assert_eq!(vec![0, 5], vec(&a));
+        let mut a: ArenaLinked<i32> = (0..10).collect();
+        a.retain(|x| x % 3 != 0);
+        assert_eq!(vec![1, 2, 4, 5, 7, 8], vec(&a));
+        assert_eq!(vec![2, 4], a.drain(1..3).collect::<Vec<_>>());
+        assert_eq!(vec![7, 8], a.drain(2..).collect::<Vec<_>>());
+        assert_eq!(vec![1], a.extract_if(|x| *x == 1).collect::<Vec<_>>());
+        a.push(6);
+        assert_eq!((vec![5, 6], 2), (vec(&a), a.len()));
+        // 游标
+        let mut a: ArenaLinked<i32> = (1..=4).collect();
+        let mut c = a.cursor_mut();
+        c.move_next();
+        assert_eq!(Some(&mut 3), c.peek_next());
+        c.insert_after(10);
+        assert_eq!(Some(2), c.remove_current());
+        let rest = c.split_after();
+        c.splice_after(ArenaLinked::from(vec![7, 8]));
+        assert_eq!(Some(&mut 10), c.current());
+        while c.move_next() {}
+        c.insert_after(9);
+        assert_eq!(vec![1, 10, 7, 8, 9], vec(&a));
+        assert_eq!(vec![3, 4], vec(&rest));
+        // 作为队列和 LRU 存储
+        let mut q: Queue<ArenaLinked<i32>, i32> = Queue::new(2);
+        q.enqueue(1);
+        q.enqueue(2);
+        assert_eq!(Some(1), q.dequeue());
+        let mut lru: Lru<ArenaLinked<i32>, i32> = Lru::new(3);
+        for x in [0, 1, 2, 0, 3] {
+            lru.lookup(x);
+        }
+        let v: Vec<i32> = lru.cache().clone().into();
+        assert_eq!(vec![3, 0, 2], v);
+    }
+
+    // 持久化链表 新旧版本共享节点
+    #[test]
+    fn persistent_list() {
+        use linked::PersistentList;
+        let empty: PersistentList<i32> = PersistentList::new();
+        assert_eq!(None, empty.head());
+        assert!(empty.tail().is_none());
+        let a = empty.cons(3).cons(2).cons(1);
+        assert_eq!((Some(&1), 3), (a.head(), a.len()));
+        // 派生新版本后旧版本不变
+        let b = a.cons(0);
+        let c = a.tail().unwrap().cons(10);
+        assert_eq!(vec![&1, &2, &3], a.iter().collect::<Vec<_>>());
+        assert_eq!(vec![&0, &1, &2, &3], b.iter().collect::<Vec<_>>());
+        assert_eq!(vec![&10, &2, &3], c.iter().collect::<Vec<_>>());
+        assert!(empty.is_empty());
+        // 共享节点
+        assert!(b.tail().unwrap().ptr_eq(&a));
+        assert!(c.tail().unwrap().ptr_eq(&a.tail().unwrap()));
+        assert!(a.clone().ptr_eq(&a));
+        assert!(!a.ptr_eq(&PersistentList::from_iter(vec![1, 2, 3])));
+        assert_eq!(a, PersistentList::from_iter(vec![1, 2, 3]));
+        // 反转与拼接
+        assert_eq!(PersistentList::from_iter(vec![3, 2, 1]), a.reverse());
+        let d = c.append(&a);
+        assert_eq!(vec![10, 2, 3, 1, 2, 3], d.iter().cloned().collect::<Vec<_>>());
+        assert_eq!(6, d.len());
+        let mut t = d.clone();
+        for _ in 0..3 {
+            t = t.tail().unwrap();
+        }
+        assert!(t.ptr_eq(&a));
+        // 与 Linked 互相转换
+        let l: Linked<i32> = (&d).into();
+        assert_eq!(6, l.len());
+        let p = PersistentList::from(l);
+        assert_eq!(d, p);
+        let l: Linked<i32> = p.into();
+        assert_eq!(vec![10, 2, 3, 1, 2, 3], Vec::from(l));
+        assert_eq!("[1, 2, 3]", format!("{:?}", a));
+        // 长链表释放不会栈溢出 共享部分保留
+        let long: PersistentList<u32> = (0..1_000_000).collect();
+        let longer = long.cons(7);
+        drop(long);
+        assert_eq!(1_000_001, longer.len());
+        assert_eq!(Some(&999_999), longer.iter().last());
+        // 不同版本可以在线程间传递
+        let handle = std::thread::spawn(move || longer.tail().map(|t| t.len()));
+        assert_eq!(Some(1_000_000), handle.join().unwrap());
+        // 两个线程同时释放共享尾部的版本 不会栈溢出
+        for _ in 0..4 {
+            let base: PersistentList<u32> = (0..300_000).collect();
+            let versions = [base.cons(1), base.cons(2)];
+            drop(base);
+            let barrier = std::sync::Arc::new(std::sync::Barrier::new(2));
+            let handles: Vec<_> = versions
+                .into_iter()
+                .map(|v| {
+                    let barrier = std::sync::Arc::clone(&barrier);
+                    std::thread::spawn(move || {
+                        barrier.wait();
+                        drop(v);
+                    })
+                })
+                .collect();
+            for h in handles {
+                h.join().unwrap();
+            }
+        }
+    }
+
+    // 不能复制的元素 删除时直接移出
+    #[test]
+    fn linked_move_only() {
+        // 闭包既不能复制也不能比较
+        let mut v: Linked<Box<dyn Fn(i32) -> i32>> = Linked::new();
+        v.push(Box::new(|x| x + 1));
+        v.push(Box::new(|x| x * 2));
+        v.push(Box::new(|x| x - 3));
+        v.insert(0, Box::new(|x| x));
+        assert_eq!(vec![5, 6, 10, 2], v.iter().map(|f| f(5)).collect::<Vec<_>>());
+        assert_eq!(Some(2), v.pop().map(|f| f(5)));
+        assert_eq!(Some(6), v.remove(1).map(|f| f(5)));
+        assert_eq!(2, v.len());
+        v.reverse();
+        v.retain(|f| f(1) > 1);
+        assert_eq!(Some(10), v.remove(0).map(|f| f(5)));
+        assert!(v.is_empty());
+        let mut q: Queue<Linked<Box<dyn Fn() -> &'static str>>, _> = Queue::new(2);
+        q.enqueue(Box::new(|| "a"));
+        assert_eq!(Some("a"), q.dequeue().map(|f| f()));
+        let mut q: Queue<List<Box<dyn Fn() -> &'static str>>, _> = Queue::new(2);
+        q.enqueue(Box::new(|| "b"));
+        assert_eq!(Some("b"), q.dequeue().map(|f| f()));
+        // 只能比较不能复制的句柄 被淘汰时移出
+        #[derive(Debug, PartialEq)]
+        struct Handle(u32);
+        let mut lru: Lru<List<Handle>, Handle> = Lru::new(2);
+        lru.lookup(Handle(1));
+        lru.lookup(Handle(2));
+        assert_eq!(lru::Outcome::Hit, lru.lookup(Handle(1)));
+        assert_eq!(lru::Outcome::MissEvicted(Handle(2)), lru.lookup(Handle(3)));
+        let mut lru: Lru<Linked<Handle>, Handle> = Lru::new(1);
+        lru.lookup(Handle(1));
+        assert_eq!(lru::Outcome::MissEvicted(Handle(1)), lru.lookup(Handle(2)));
+    }
+
+    // 双向链表 两端 O(1) 压入弹出
+    #[test]
+    fn doubly_linked() {
+        use linked::DoublyLinked;
+        let mut v: DoublyLinked<i32> = DoublyLinked::new();
+        assert_eq!(None, v.pop_front());
+        assert_eq!(None, v.pop_back());
+        v.push_back(2);
+        v.push_front(1);
+        v.push_back(3);
+        v.push_front(0);
+        assert_eq!((Some(&0), Some(&3)), (v.front(), v.back()));
+        assert_eq!(Some(&2), v.index(2));
+        assert_eq!(None, v.index(4));
+        assert_eq!(Some(3), v.pop_back());
+        assert_eq!(Some(0), v.pop_front());
+        assert_eq!(2, v.len());
+        // 索引插入删除 空位复用
+        v.insert(1, 5);
+        v.insert(3, 6);
+        v.insert(9, 7);
+        assert_eq!(Some(5), v.remove(1));
+        assert!(v.contains(&6));
+        assert!(!v.contains(&5));
+        let v1: Vec<i32> = v.into();
+        assert_eq!(vec![1, 2, 6], v1);
+        // 大量两端操作
+        let mut v = DoublyLinked::new();
+        for i in 0..1_000_000 {
+            v.push_back(i);
+        }
+        for i in 0..500_000 {
+            assert_eq!(Some(i), v.pop_front());
+            assert_eq!(Some(999_999 - i), v.pop_back());
+        }
+        assert!(v.is_empty());
+        // 作为队列存储
+        let mut q: Queue<DoublyLinked<i32>, i32> = Queue::new(3);
+        q.enqueue(1);
+        q.enqueue(2);
+        q.enqueue(3);
+        assert!(!q.enqueue(4));
+        assert_eq!(Some(1), q.dequeue());
+        assert_eq!(Some(2), q.dequeue());
+        assert_eq!(Some(3), q.dequeue());
+        assert_eq!(None, q.dequeue());
+        // 作为 LRU 存储
+        let mut lru: Lru<DoublyLinked<i32>, i32> = Lru::new(3);
+        for x in [0, 1, 2, 0, 3] {
+            lru.lookup(x);
+        }
+        let v1: Vec<i32> = lru.cache().clone().into();
+        assert_eq!(vec![3, 0, 2], v1);
+        assert_eq!(lru::Outcome::MissEvicted(2), lru.lookup(4));
+        assert_eq!(Err(lru::CacheError::Empty), Cache::pop(&mut DoublyLinked::<i32>::new()));
+    }
+
     // 栈 表达式求值/括号匹配/前进后退
     #[test]
     fn stack() {
@@ -199,9 +1539,210 @@ mod tests {
         assert_eq!(false, stack::brackets_match(s));
         // 计算表达式
         let s = "3 + 5 * 8 - 6";
-        assert_eq!(Some(3 + 5 * 8 - 6), stack::calculate(s));
+        assert_eq!(Ok(37.0), stack::calculate(s));
         let s = "9 / 5 + 3 - 1 + 3 * 2 - 1";
-        assert_eq!(Some(9 / 5 + 3 - 1 + 3 * 2 - 1), stack::calculate(s))
+        assert_eq!(Ok(9.0 / 5.0 + 3.0 - 1.0 + 3.0 * 2.0 - 1.0), stack::calculate(s))
+    }
+
+    // 表达式求值 多位数/小数/括号/负号/取余/乘方 以及带位置的错误
+    #[test]
+    fn stack_calculate() {
+        use crate::stack::CalcError;
+        assert_eq!(Ok(15.0), stack::calculate("12 + 3"));
+        assert_eq!(Ok(0.75), stack::calculate("1.5 * .5"));
+        assert_eq!(Ok(20.0), stack::calculate("(1 + 4) * (10 - 6)"));
+        assert_eq!(Ok(-7.0), stack::calculate("-(3 + 4)"));
+        assert_eq!(Ok(6.0), stack::calculate("2 * -3 * -1"));
+        assert_eq!(Ok(2.0), stack::calculate("3 - -1 - 2 + 0"));
+        assert_eq!(Ok(1.0), stack::calculate("17 % 7 - 1 % 2 * 4 + 2"));
+        // 乘方右结合 优先级高于负号
+        assert_eq!(Ok(512.0), stack::calculate("2 ^ 3 ^ 2"));
+        assert_eq!(Ok(-4.0), stack::calculate("-2 ^ 2"));
+        assert_eq!(Ok(0.5), stack::calculate("2 ^ -1"));
+        assert_eq!(Ok(64.0), stack::calculate("(2 ^ 3) ^ 2"));
+        // 长度不受限制
+        let long = vec!["1"; 1000].join(" + ");
+        assert_eq!(Ok(1000.0), stack::calculate(&long));
+        let nested = format!("{}7{}", "(".repeat(500), ")".repeat(500));
+        assert_eq!(Ok(7.0), stack::calculate(&nested));
+        // 错误及位置
+        assert_eq!(Err(CalcError::DivisionByZero { pos: 2 }), stack::calculate("1 / (2 - 2)"));
+        assert_eq!(Err(CalcError::DivisionByZero { pos: 6 }), stack::calculate("1 + 5 % 0"));
+        assert_eq!(Err(CalcError::MismatchedParen { pos: 0 }), stack::calculate("(1 + 2"));
+        assert_eq!(Err(CalcError::MismatchedParen { pos: 5 }), stack::calculate("1 + 2) * 3"));
+        let err = CalcError::UnexpectedToken { pos: 4, token: "$".to_string() };
+        assert_eq!(Err(err), stack::calculate("1 + $"));
+        let err = CalcError::UnexpectedToken { pos: 2, token: "34".to_string() };
+        assert_eq!(Err(err), stack::calculate("1 34"));
+        let err = CalcError::UnexpectedToken { pos: 3, token: ")".to_string() };
+        assert_eq!(Err(err), stack::calculate("(1+)"));
+        let err = CalcError::UnexpectedToken { pos: 0, token: "1.2.3".to_string() };
+        assert_eq!(Err(err), stack::calculate("1.2.3"));
+        assert_eq!(Err(CalcError::UnexpectedEnd { pos: 4 }), stack::calculate("2 * "));
+        assert_eq!(Err(CalcError::UnexpectedEnd { pos: 0 }), stack::calculate(""));
+        assert_eq!("division by zero at 2", stack::calculate("1 / 0").unwrap_err().to_string());
+        assert_eq!(4, stack::calculate("1 + )").unwrap_err().pos());
+    }
+
+    // 中缀转后缀 逆波兰表达式求值 语法树输出最少括号
+    #[test]
+    fn stack_postfix() {
+        use crate::stack::{CalcError, Expr, Op, Token, TokenKind};
+        let rpn = |s: &str| -> String {
+            let v: Vec<String> = stack::to_postfix(s).unwrap().iter().map(|t| t.to_string()).collect();
+            v.join(" ")
+        };
+        assert_eq!("3 5 8 * + 6 -", rpn("3 + 5 * 8 - 6"));
+        assert_eq!("1 2 + 3 4 - *", rpn("(1 + 2) * (3 - 4)"));
+        assert_eq!("2 3 2 ^ ^", rpn("2 ^ 3 ^ 2"));
+        assert_eq!("2 neg 3 *", rpn("-2 * 3"));
+        assert_eq!("2 2 ^ neg", rpn("-2 ^ 2"));
+        assert_eq!("1.5 2 1 neg neg ^ %", rpn("1.5 % 2 ^ --1"));
+        let tokens = stack::to_postfix("12 / (4 - 4)").unwrap();
+        assert_eq!(Token { kind: TokenKind::Num(12.0), pos: 0 }, tokens[0]);
+        assert_eq!(Token { kind: TokenKind::Op(Op::Div), pos: 3 }, tokens[4]);
+        assert_eq!(Err(CalcError::DivisionByZero { pos: 3 }), stack::eval_rpn(&tokens));
+        assert_eq!(Ok(37.0), stack::eval_rpn(&stack::to_postfix("3 + 5 * 8 - 6").unwrap()));
+        assert_eq!(Err(CalcError::MismatchedParen { pos: 0 }), stack::to_postfix("(1 + 2"));
+        // 手工构造的后缀表达式 操作数不足或多余
+        let num = |n, pos| Token { kind: TokenKind::Num(n), pos };
+        let add = Token { kind: TokenKind::Op(Op::Add), pos: 1 };
+        let err = CalcError::UnexpectedToken { pos: 1, token: "+".to_string() };
+        assert_eq!(Err(err), stack::eval_rpn(&[num(1.0, 0), add]));
+        let err = CalcError::UnexpectedToken { pos: 2, token: "2".to_string() };
+        assert_eq!(Err(err), stack::eval_rpn(&[num(1.0, 0), num(2.0, 2)]));
+        assert_eq!(Err(CalcError::UnexpectedEnd { pos: 0 }), stack::eval_rpn(&[]));
+        // 语法树
+        let e = stack::parse("-(1 + 2)").unwrap();
+        let three = Expr::Binary { op: Op::Add, pos: 4, lhs: Box::new(Expr::Num(1.0)), rhs: Box::new(Expr::Num(2.0)) };
+        assert_eq!(Expr::Neg { pos: 0, expr: Box::new(three) }, e);
+        assert_eq!(Ok(-3.0), e.eval());
+        let pretty = |s: &str| stack::parse(s).unwrap().to_string();
+        assert_eq!("1 + 2 * 3", pretty("((1) + (2 * 3))"));
+        assert_eq!("(1 + 2) * 3", pretty("((1 + 2)) * 3"));
+        assert_eq!("1 - (2 - 3)", pretty("1 - (2 - 3)"));
+        assert_eq!("1 - 2 - 3", pretty("(1 - 2) - 3"));
+        assert_eq!("1 / (2 * 3) % 4", pretty("(1 / (2 * 3)) % 4"));
+        assert_eq!("2 ^ 3 ^ 2", pretty("2 ^ (3 ^ 2)"));
+        assert_eq!("(2 ^ 3) ^ 2", pretty("(2 ^ 3) ^ 2"));
+        assert_eq!("(-2) ^ 2", pretty("(-2) ^ 2"));
+        assert_eq!("-2 ^ 2", pretty("-(2 ^ 2)"));
+        assert_eq!("2 ^ -1.5 * 4", pretty("(2 ^ (-1.5)) * 4"));
+        assert_eq!("-(1 - 2) * -3", pretty("(-(1 - 2)) * (-3)"));
+        // 输出再解析得到相同的语法树和结果
+        for s in ["(1 - 2) - (3 - (4 ^ 2 ^ -1)) * -(5 % 3)", "-(-(2)) ^ 2 / ((8))"] {
+            let e = stack::parse(s).unwrap();
+            let again = stack::parse(&e.to_string()).unwrap();
+            assert_eq!(e.to_string(), again.to_string());
+            assert_eq!(e.eval(), again.eval());
+            assert_eq!(stack::calculate(s), e.eval());
+            // 语法树转换的后缀表达式与直接转换的一致（数字没有位置）
+            let kinds = |v: Vec<Token>| v.into_iter().map(|t| t.kind).collect::<Vec<_>>();
+            assert_eq!(kinds(stack::to_postfix(s).unwrap()), kinds(e.to_postfix()));
+        }
+        // 很深的语法树 求值、输出和释放都不会栈溢出
+        let s = vec!["1"; 100_000].join(" + ");
+        let e = stack::parse(&s).unwrap();
+        assert_eq!(Ok(100_000.0), e.eval());
+        assert_eq!(s, e.to_string());
+        drop(e);
+        let s = format!("{}2 ^ {}", "-".repeat(100_001), "(3 + ".repeat(50_000) + "1" + &")".repeat(50_000));
+        let e = stack::parse(&s).unwrap();
+        assert_eq!(stack::calculate(&s), e.eval());
+        assert_eq!(Ok(-f64::INFINITY), e.eval());
+        let again = stack::parse(&e.to_string()).unwrap();
+        assert_eq!(e.eval(), again.eval());
+    }
+
+    // 计算环境 变量/函数/整数 浮点数 有理数三种计算模式
+    #[test]
+    fn stack_env() {
+        use crate::stack::{ArithError, CalcError, Env, Rational};
+        let mut env: Env = Env::new();
+        env.set("a", 3.0);
+        env.set("b", 4.0);
+        env.set("rate", 2.0);
+        assert_eq!(Ok(4.0), env.calculate("max(a, b * 2) / rate"));
+        assert_eq!(Ok(-1.0), env.calculate("min(a, -b ^ 0, 7) + sum()"));
+        assert_eq!(Ok(15.0), env.calculate("sum(a, b, max(a), min (b, a) + 2)"));
+        env.function("hypot", 2, |v: &[f64]| Ok((v[0] * v[0] + v[1] * v[1]).sqrt()));
+        env.variadic("avg", 1, |v: &[f64]| Ok(v.iter().sum::<f64>() / v.len() as f64));
+        assert_eq!(Ok(5.0), env.calculate("hypot(a, b)"));
+        assert_eq!(Ok(3.0), env.calculate("avg(1, 2, 3, hypot(a, b) + 1)"));
+        // 编译一次 修改变量后重复计算
+        let rpn = stack::to_postfix("a * rate - hypot(a, b)").unwrap();
+        let v: Vec<String> = rpn.iter().map(|t| t.to_string()).collect();
+        assert_eq!("a rate * a b hypot/2 -", v.join(" "));
+        assert_eq!(Ok(1.0), env.eval_rpn(&rpn));
+        env.set("rate", 10.0);
+        assert_eq!(Ok(25.0), env.eval_rpn(&rpn));
+        // 语法树 函数调用和变量
+        let e = stack::parse("-max(a, (b - 1)) * 2").unwrap();
+        assert_eq!("-max(a, b - 1) * 2", e.to_string());
+        assert_eq!(Ok(-6.0), env.eval(&e));
+        // 很深的语法树 变量和函数调用同样按栈计算，错误位置与直接计算一致
+        let s = vec!["max(a, b - 1)"; 50_000].join(" - ");
+        let e = stack::parse(&s).unwrap();
+        assert_eq!(env.calculate(&s), env.eval(&e));
+        assert_eq!(Ok(3.0 - 3.0 * 49_999.0), env.eval(&e));
+        let s = format!("{}c", "a + ".repeat(100_000));
+        let err = CalcError::UnknownVariable { pos: s.len() - 1, name: "c".to_string() };
+        assert_eq!(Err(err), env.eval(&stack::parse(&s).unwrap()));
+        // 错误
+        let err = CalcError::UnknownVariable { pos: 4, name: "c".to_string() };
+        assert_eq!(Err(err), env.calculate("a + c"));
+        let err = CalcError::UnknownFunction { pos: 2, name: "foo".to_string() };
+        assert_eq!(Err(err), env.calculate("1+foo(2)"));
+        let err = CalcError::Arity { pos: 0, name: "hypot".to_string(), argc: 3 };
+        assert_eq!(Err(err), env.calculate("hypot(1, 2, 3)"));
+        let err = CalcError::Arity { pos: 0, name: "max".to_string(), argc: 0 };
+        assert_eq!(Err(err), env.calculate("max()"));
+        let err = CalcError::UnexpectedToken { pos: 6, token: ",".to_string() };
+        assert_eq!(Err(err), env.calculate("max((1, 2))"));
+        let err = CalcError::UnexpectedToken { pos: 7, token: ")".to_string() };
+        assert_eq!(Err(err), env.calculate("max(1, )"));
+        assert_eq!(Err(CalcError::MismatchedParen { pos: 0 }), env.calculate("max(1, 2"));
+        env.function("inv", 1, |v: &[f64]| if v[0] == 0.0 { Err(ArithError::DivisionByZero) } else { Ok(1.0 / v[0]) });
+        assert_eq!(Err(CalcError::DivisionByZero { pos: 4 }), env.calculate("2 * inv(a - 3)"));
+        // 整数模式 除法向 0 取整 溢出返回错误
+        let mut env: Env<i64> = Env::new();
+        env.set("n", i64::MAX);
+        assert_eq!(Ok(3), env.calculate("7 / 2"));
+        assert_eq!(Ok(-1), env.calculate("-7 % 3 + 10 / -4 + 2 ^ 1"));
+        assert_eq!(Ok(1 << 62), env.calculate("2 ^ 62"));
+        assert_eq!(Ok(i64::MAX), env.calculate("max(n, 1, -n)"));
+        assert_eq!(Err(CalcError::Overflow { pos: 2 }), env.calculate("n + 1"));
+        assert_eq!(Err(CalcError::Overflow { pos: 7 }), env.calculate("-n - 1 - 1"));
+        assert_eq!(Err(CalcError::Overflow { pos: 2 }), env.calculate("2 ^ 63"));
+        assert_eq!(Err(CalcError::Overflow { pos: 0 }), env.calculate("sum(n, n)"));
+        assert_eq!(Err(CalcError::Overflow { pos: 0 }), env.calculate("9223372036854775808"));
+        assert_eq!(Err(CalcError::Domain { pos: 2 }), env.calculate("2 ^ -1"));
+        assert_eq!(Err(CalcError::DivisionByZero { pos: 2 }), env.calculate("1 / (n - n)"));
+        let err = CalcError::UnexpectedToken { pos: 4, token: "1.5".to_string() };
+        assert_eq!(Err(err), env.calculate("2 * 1.5"));
+        // 有理数模式 精确计算
+        let r = |n, d| Rational::new(n, d).unwrap();
+        let mut env: Env<Rational> = Env::new();
+        assert_eq!(Ok(r(3, 10)), env.calculate("0.1 + 0.2"));
+        assert_eq!(Ok(r(1, 1)), env.calculate("1 / 3 * 3"));
+        assert_eq!(Ok(r(-8, 27)), env.calculate("(-2 / 3) ^ 3"));
+        assert_eq!(Ok(r(9, 4)), env.calculate("(2 / 3) ^ -2"));
+        assert_eq!(Ok(r(1, 6)), env.calculate("7 / 6 % (1 / 2)"));
+        env.set("x", r(5, 4));
+        assert_eq!(Ok(r(5, 4)), env.calculate("max(1, x, 1.2)"));
+        assert_eq!(Ok(r(7, 2)), env.calculate("sum(x, 1.25, 1)"));
+        assert_eq!("5/4", r(10, 8).to_string());
+        assert_eq!((-3, 4), (r(3, -4).numer(), r(3, -4).denom()));
+        assert_eq!(None, Rational::new(1, 0));
+        assert!(r(1, 3) < r(1, 2));
+        assert_eq!(0.75, r(3, 4).to_f64());
+        assert_eq!(Err(CalcError::Domain { pos: 2 }), env.calculate("2 ^ 0.5"));
+        assert_eq!(Err(CalcError::DivisionByZero { pos: 2 }), env.calculate("0 ^ -1"));
+        assert_eq!(Err(CalcError::Overflow { pos: 20 }), env.calculate("9223372036854775807 + 1"));
+        // 分数作为左操作数输出时加括号
+        let e = stack::parse_as::<Rational>("0.5 ^ 2 - -1").unwrap();
+        assert_eq!("(1/2) ^ 2 - -1", e.to_string());
+        assert_eq!(Ok(r(5, 4)), env.eval(&stack::parse_as(&e.to_string()).unwrap()));
     }
 
     // 队列
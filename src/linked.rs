@@ -1,120 +1,261 @@
-use crate::lru::Cache;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+use crate::lru::{Cache, CacheError};
 use crate::queue::QueueCache;
 
 // 定义节点
 #[derive(Clone, Debug)]
-struct Node<T> where T: Clone + PartialOrd + PartialEq {
+struct Node<T> {
     element: T,
     next: Option<Box<Node<T>>>,
 }
 
-impl<T> Node<T> where T: Clone + PartialOrd + PartialEq {
+impl<T> Node<T> {
     fn new(elt: T) -> Self {
         Node {
             element: elt,
             next: None,
         }
     }
-    // 是否包涵
-    fn contains(&mut self, n: &T) -> bool {
-        if &self.element == n {
-            return true;
-        } else {
-            if let Some(ref mut node) = self.next {
-                return node.contains(n);
+    // 是否包涵 以下均为循环实现 长链表不会栈溢出
+    fn contains(&self, n: &T) -> bool where T: PartialEq {
+        let mut node = Some(self);
+        while let Some(x) = node {
+            if &x.element == n {
+                return true;
             }
+            node = x.next.as_deref();
         }
         false
     }
     // 返回最后一个节点
     fn last(&mut self) -> &mut Self {
-        if let Some(ref mut node) = self.next {
-            return node.last();
+        let mut node = self;
+        // 已经判断 Some 则 unwrap 无风险（直接 while let 借用检查无法通过）
+        while node.next.is_some() {
+            node = node.next.as_mut().unwrap();
         }
-        self
+        node
     }
-    // 根据索引下标查找节点
+    // 根据索引下标查找节点 n 为当前节点的下标
     fn search(&mut self, n: usize, i: usize) -> Option<&mut Self> {
-        // 坐标相同时返回
-        if n == i {
-            return Some(self);
-        } else {
-            // 递归调用 所有权机制循环难以处理
-            if let Some(ref mut node) = self.next {
-                return node.search(n + 1, i);
-            }
+        let mut node = self;
+        for _ in n..i {
+            node = node.next.as_deref_mut()?;
         }
-        None
+        Some(node)
     }
 }
 
-// 合并有序 Node 递归实现
-impl<T> Node<T> where T: Clone + PartialOrd + PartialEq {
-    fn merge(n: Option<Box<Node<T>>>, m: Option<Box<Node<T>>>) -> Option<Box<Node<T>>> {
-        match (n, m) {
-            (Some(x), None) => {
-                Some(x)
-            }
-            (None, Some(y)) => {
-                Some(y)
-            }
-            (Some(x), Some(y)) => {
-                if x.element > y.element {
-                    Some(Box::from(
-                        Node {
-                            element: y.element,
-                            next: Node::merge(Some(x), y.next),
-                        }
-                    ))
-                } else {
-                    Some(Box::from(
-                        Node {
-                            element: x.element,
-                            next: Node::merge(x.next, Some(y)),
-                        }
-                    ))
+// 合并有序 Node 循环实现，直接把节点接到结果末尾不重新分配
+// 元素相等时先取 n 中的节点 保持稳定
+impl<T> Node<T> {
+    fn merge(n: Option<Box<Node<T>>>, m: Option<Box<Node<T>>>) -> Option<Box<Node<T>>> where T: PartialOrd {
+        Node::merge_by(n, m, &mut |a: &T, b: &T| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+    // 按比较函数合并 只有 n 的元素大于 m 的元素时才先取 m
+    fn merge_by<F>(mut n: Option<Box<Node<T>>>, mut m: Option<Box<Node<T>>>, cmp: &mut F) -> Option<Box<Node<T>>> where F: FnMut(&T, &T) -> Ordering {
+        let mut head = None;
+        let mut tail = &mut head;
+        loop {
+            let next = match (n.take(), m.take()) {
+                (Some(mut x), Some(mut y)) => {
+                    if cmp(&x.element, &y.element) == Ordering::Greater {
+                        m = y.next.take();
+                        n = Some(x);
+                        y
+                    } else {
+                        n = x.next.take();
+                        m = Some(y);
+                        x
+                    }
                 }
-            }
-            (_, _) => {
-                None
-            }
+                // 剩余部分整体接上
+                (Some(x), None) | (None, Some(x)) => {
+                    *tail = Some(x);
+                    break;
+                }
+                (None, None) => break,
+            };
+            tail = &mut tail.insert(next).next;
         }
+        head
     }
 }
 
 // 单链表 head 头节点 len 长度
-#[derive(Clone, Debug)]
-pub struct Linked<T> where T: Clone + PartialOrd + PartialEq {
+pub struct Linked<T> {
     head: Option<Box<Node<T>>>,
     len: usize,
 }
 
+// 派生的 Drop、Clone、Debug 会沿着 Box<Node> 递归，长链表会栈溢出，这里都改为循环实现
+impl<T> Drop for Linked<T> {
+    fn drop(&mut self) {
+        // 逐个取下 head 节点 释放时 next 已经为 None
+        let mut node = self.head.take();
+        while let Some(mut x) = node {
+            node = x.next.take();
+        }
+    }
+}
+
+impl<T> Clone for Linked<T> where T: Clone {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> Debug for Linked<T> where T: Debug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
 // Vec => Linked 转换
-impl<T> From<Vec<T>> for Linked<T> where T: Clone + PartialOrd + PartialEq {
+impl<T> From<Vec<T>> for Linked<T> {
     fn from(v: Vec<T>) -> Self {
+        v.into_iter().collect()
+    }
+}
+
+// Linked => Vec 转换
+impl<T> From<Linked<T>> for Vec<T> {
+    fn from(l: Linked<T>) -> Self {
+        let mut v = Vec::with_capacity(l.len);
+        v.extend(l);
+        v
+    }
+}
+
+// 迭代器 从 head 到 tail 依次返回元素引用
+// 单链表没有 prev 指针，从 tail 反向迭代每一步都要从头查找，所以不实现 DoubleEndedIterator
+pub struct Iter<'a, T> {
+    next: Option<&'a Node<T>>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| {
+            self.next = node.next.as_deref();
+            self.len -= 1;
+            &node.element
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+// 可变迭代器
+pub struct IterMut<'a, T> {
+    next: Option<&'a mut Node<T>>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.take().map(|node| {
+            self.next = node.next.as_deref_mut();
+            self.len -= 1;
+            &mut node.element
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+// 按值迭代 依次取下 head 节点
+pub struct IntoIter<T>(Linked<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.head.take().map(|node| {
+            self.0.head = node.next;
+            self.0.len -= 1;
+            node.element
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for Linked<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Linked<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Linked<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> FromIterator<T> for Linked<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
         let mut l = Linked::new();
-        for i in v {
-            l.push(i)
-        }
+        l.extend(iter);
         l
     }
 }
 
-// Linked => Vec 转换
-impl<T> Into<Vec<T>> for Linked<T> where T: Clone + PartialOrd + PartialEq {
-    fn into(mut self) -> Vec<T> {
-        let mut v = Vec::new();
-        for i in 0..self.len {
-            if let Some(n) = self.index(i) {
-                v.push(n.clone());
-            }
+// 末尾追加 只查找一次最后一个节点 整体 O(n + m)
+impl<T> Extend<T> for Linked<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut tail = &mut self.head;
+        while let Some(node) = tail {
+            tail = &mut node.next;
+        }
+        for x in iter {
+            let node = tail.insert(Box::from(Node::new(x)));
+            tail = &mut node.next;
+            self.len += 1;
         }
-        v
     }
 }
 
 // 实现 Queue 特性
-impl<T> QueueCache<T> for Linked<T> where T: Clone + PartialOrd + PartialEq {
+impl<T> QueueCache<T> for Linked<T> {
     fn new() -> Self {
         Linked {
             head: None,
@@ -132,7 +273,7 @@ impl<T> QueueCache<T> for Linked<T> where T: Clone + PartialOrd + PartialEq {
 }
 
 // 实现 Cache Trait 作为 LRU 得存储结构支持
-impl<T> Cache<T> for Linked<T> where T: Clone + PartialOrd + PartialEq {
+impl<T> Cache<T> for Linked<T> where T: PartialEq {
     fn new() -> Self where Self: Sized {
         Linked {
             head: None,
@@ -140,28 +281,38 @@ impl<T> Cache<T> for Linked<T> where T: Clone + PartialOrd + PartialEq {
         }
     }
 
+    fn len(&self) -> usize {
+        self.len
+    }
+
     fn contains(&mut self, n: &T) -> bool {
         self.contains(n)
     }
 
-    fn remove(&mut self, i: usize) -> Option<T> {
-        self.remove(i)
+    fn remove(&mut self, i: usize) -> Result<T, CacheError> {
+        let len = self.len;
+        self.remove(i).ok_or(CacheError::OutOfBounds { index: i, len })
     }
 
-    fn insert(&mut self, i: usize, n: T) {
-        self.insert(i, n)
+    fn insert(&mut self, i: usize, n: T) -> Result<(), CacheError> {
+        if i > self.len {
+            return Err(CacheError::OutOfBounds { index: i, len: self.len });
+        }
+        self.insert(i, n);
+        Ok(())
     }
 
-    fn pop(&mut self) -> Option<T> {
-        self.pop()
+    fn pop(&mut self) -> Result<T, CacheError> {
+        self.pop().ok_or(CacheError::Empty)
     }
 
-    fn index(&mut self, i: usize) -> Option<&T> {
-        self.index(i)
+    fn index(&mut self, i: usize) -> Result<&T, CacheError> {
+        let len = self.len;
+        self.index(i).ok_or(CacheError::OutOfBounds { index: i, len })
     }
 }
 
-impl<T> Linked<T> where T: Clone + PartialOrd + PartialEq {
+impl<T> Linked<T> {
     pub fn new() -> Self {
         Linked {
             head: None,
@@ -200,29 +351,25 @@ impl<T> Linked<T> where T: Clone + PartialOrd + PartialEq {
         }
         self.len += 1;
     }
-    // 根据索引下标删除节点
+    // 根据索引下标删除节点 取下节点后直接移出元素 不需要复制
     pub fn remove(&mut self, i: usize) -> Option<T> {
-        if let Some(ref mut head) = self.head {
-            // 处理索引为 0 的情况
-            if i == 0 {
-                let node = head.element.clone();
-                self.head = head.next.take();
-                self.len -= 1;
-                return Some(node);
-            } else {
-                // 处理其他情况
-                if let Some(prev) = head.search(0, i - 1) {
-                    // 临时变量 next 存储节点
-                    if let Some(next) = prev.next.take() {
-                        // prev = next => next
-                        prev.next = next.next;
-                        self.len -= 1;
-                        return Some(next.element.clone());
-                    }
-                }
-            }
+        // 处理索引为 0 的情况
+        if i == 0 {
+            let head = self.head.take()?;
+            let Node { element, next } = *head;
+            self.head = next;
+            self.len -= 1;
+            return Some(element);
         }
-        None
+        // 处理其他情况
+        let prev = self.head.as_mut()?.search(0, i - 1)?;
+        // 临时变量 next 存储节点
+        let next = prev.next.take()?;
+        let Node { element, next } = *next;
+        // prev = next => next
+        prev.next = next;
+        self.len -= 1;
+        Some(element)
     }
     // 索引插入节点
     pub fn insert(&mut self, i: usize, n: T) {
@@ -233,15 +380,15 @@ impl<T> Linked<T> where T: Clone + PartialOrd + PartialEq {
                 // 通过 take 取出 head 内的值
                 new.next = self.head.take();
                 self.head = Some(Box::from(new));
+                self.len += 1;
             } else {
                 if let Some(prev) = head.search(0, i - 1) {
-                    if let Some(next) = prev.next.take() {
-                        new.next = Option::from(next);
-                        prev.next = Some(Box::from(new));
-                    }
+                    // 索引等于长度时 next 为 None 相当于末尾插入
+                    new.next = prev.next.take();
+                    prev.next = Some(Box::from(new));
+                    self.len += 1;
                 }
             }
-            self.len += 1;
         } else {
             self.head = Some(Box::from(new));
             self.len += 1;
@@ -249,27 +396,11 @@ impl<T> Linked<T> where T: Clone + PartialOrd + PartialEq {
     }
     // 末尾删除节点
     pub fn pop(&mut self) -> Option<T> {
-        if let Some(ref mut head) = self.head {
-            // 处理只有一个节点的情况
-            if self.len == 1 {
-                let last = head.element.clone();
-                self.head = head.next.take();
-                self.len -= 1;
-                return Some(last);
-            }
-            // 处理其他情况
-            let last = head.last().element.clone();
-            if let Some(prev) = head.search(0, self.len - 2) {
-                prev.next = None;
-                self.len -= 1;
-                return Some(last);
-            }
-        }
-        None
+        self.remove(self.len.checked_sub(1)?)
     }
     // 是否包含
-    pub fn contains(&mut self, n: &T) -> bool {
-        if let Some(ref mut head) = self.head {
+    pub fn contains(&mut self, n: &T) -> bool where T: PartialEq {
+        if let Some(ref head) = self.head {
             return head.contains(n);
         }
         false
@@ -289,12 +420,157 @@ impl<T> Linked<T> where T: Clone + PartialOrd + PartialEq {
     pub fn len(&self) -> usize {
         self.len
     }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head.as_deref(),
+            len: self.len,
+        }
+    }
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            next: self.head.as_deref_mut(),
+            len: self.len,
+        }
+    }
+    // 从 head 开始的可变游标
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            link: Some(&mut self.head),
+            len: &mut self.len,
+            index: 0,
+        }
+    }
+}
+
+// 可变游标 遍历时原地插入删除 不需要每次从 head 重新查找
+// link 指向保存当前节点的位置（head 或前一个节点的 next），删除当前节点只需改写这个位置
+// 当前节点为 None 时游标在末尾之后
+pub struct CursorMut<'a, T> {
+    // 总是 Some，用 Option 包装以便向后移动时取出引用重新借用
+    link: Option<&'a mut Option<Box<Node<T>>>>,
+    len: &'a mut usize,
+    index: usize,
+}
+
+impl<T> CursorMut<'_, T> {
+    // 当前下标 在末尾之后时等于长度
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    // 当前元素 可直接修改
+    pub fn current(&mut self) -> Option<&mut T> {
+        self.node().map(|n| &mut n.element)
+    }
+    // 下一个元素
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        self.node()?.next.as_mut().map(|n| &mut n.element)
+    }
+    // 向后移动一个节点 已经在末尾之后时返回 false
+    pub fn move_next(&mut self) -> bool {
+        match self.link.take() {
+            Some(Some(node)) => {
+                self.link = Some(&mut node.next);
+                self.index += 1;
+                true
+            }
+            other => {
+                self.link = other;
+                false
+            }
+        }
+    }
+    // 在当前节点后面插入，在末尾之后时插入到末尾并成为当前节点
+    pub fn insert_after(&mut self, n: T) {
+        let mut new = Box::from(Node::new(n));
+        match self.node() {
+            Some(node) => {
+                new.next = node.next.take();
+                node.next = Some(new);
+            }
+            None => {
+                if let Some(link) = self.link.as_mut() {
+                    **link = Some(new);
+                }
+            }
+        }
+        *self.len += 1;
+    }
+    // 删除当前节点 游标指向原来的下一个节点
+    pub fn remove_current(&mut self) -> Option<T> {
+        let link = self.link.as_mut()?;
+        let mut node = link.take()?;
+        **link = node.next.take();
+        *self.len -= 1;
+        Some(node.element)
+    }
+    // 从当前节点后面断开 返回后半部分，根据游标下标直接计算两部分长度
+    pub fn split_after(&mut self) -> Linked<T> {
+        let index = self.index;
+        match self.node() {
+            Some(node) => {
+                let rest = Linked {
+                    head: node.next.take(),
+                    len: *self.len - index - 1,
+                };
+                *self.len = index + 1;
+                rest
+            }
+            None => Linked::new(),
+        }
+    }
+    // 从当前节点前面断开 返回从当前节点开始的部分，游标停在末尾之后
+    pub fn split_before(&mut self) -> Linked<T> {
+        let index = self.index;
+        match self.link.as_mut() {
+            Some(link) => {
+                let rest = Linked {
+                    head: link.take(),
+                    len: *self.len - index,
+                };
+                *self.len = index;
+                rest
+            }
+            None => Linked::new(),
+        }
+    }
+    // 把另一个链表整体插入到当前节点后面，在末尾之后时接到末尾
+    // 单链表需要找到 other 的最后一个节点 复杂度 O(m) 与当前链表长度无关
+    pub fn splice_after(&mut self, mut other: Linked<T>) {
+        let head = match other.head.take() {
+            Some(head) => head,
+            None => return,
+        };
+        let m = other.len;
+        let link = match self.link.as_mut() {
+            Some(link) => link,
+            None => return,
+        };
+        let slot = match link.as_mut() {
+            Some(node) => &mut node.next,
+            None => &mut **link,
+        };
+        let rest = slot.take();
+        slot.insert(head).last().next = rest;
+        *self.len += m;
+    }
+    fn node(&mut self) -> Option<&mut Node<T>> {
+        self.link.as_mut()?.as_deref_mut()
+    }
+}
+
+impl<T> Default for Linked<T> {
+    fn default() -> Self {
+        Linked::new()
+    }
 }
 
-impl<T> Linked<T> where T: Clone + PartialOrd + PartialEq {
+impl<T> Linked<T> {
     // 合并有序列表
-    pub fn merge_order(n: Linked<T>, m: Linked<T>) -> Option<Linked<T>> {
-        if let Some(node) = Node::merge(n.head, m.head) {
+    pub fn merge_order(mut n: Linked<T>, mut m: Linked<T>) -> Option<Linked<T>> where T: PartialOrd {
+        if let Some(node) = Node::merge(n.head.take(), m.head.take()) {
             // 调用 Node::merge
             return Some(Linked {
                 head: Some(node),
@@ -304,4 +580,2108 @@ impl<T> Linked<T> where T: Clone + PartialOrd + PartialEq {
         }
         None
     }
+}
+
+// 经典链表算法
+impl<T> Linked<T> {
+    // 中间节点 快指针每次走 2 步 慢指针走 1 步，快指针到头时慢指针为中间节点（偶数个取后一个）
+    pub fn middle(&self) -> Option<&T> {
+        let mut slow = self.head.as_deref()?;
+        let mut fast = self.head.as_deref();
+        while let Some(next) = fast.and_then(|f| f.next.as_deref()) {
+            slow = slow.next.as_deref()?;
+            fast = next.next.as_deref();
+        }
+        Some(&slow.element)
+    }
+    // 删除倒数第 n 个节点（n 从 1 开始） 一次遍历
+    // 快指针领先慢指针 n 个节点，两者之间的节点暂存在队列中；慢指针是链表末尾的可变链接，
+    // 快指针每前进一步，离开窗口的节点一定不是倒数第 n 个，直接接回慢指针并后移。快指针到头时队首就是待删除节点
+    // Box 链表不能同时持有慢指针的可变引用和快指针的只读引用，所以窗口内的节点按所有权移动，额外空间 O(n)
+    pub fn remove_nth_from_end(&mut self, n: usize) -> Option<T> {
+        if n == 0 {
+            return None;
+        }
+        let mut window: VecDeque<Box<Node<T>>> = VecDeque::with_capacity(n.min(self.len));
+        let mut fast = self.head.take();
+        let mut slow = &mut self.head;
+        while let Some(mut node) = fast {
+            fast = node.next.take();
+            window.push_back(node);
+            if window.len() > n {
+                if let Some(front) = window.pop_front() {
+                    slow = &mut slow.insert(front).next;
+                }
+            }
+        }
+        let removed = if window.len() == n { window.pop_front() } else { None };
+        for node in window {
+            slow = &mut slow.insert(node).next;
+        }
+        let node = removed?;
+        self.len -= 1;
+        Some(node.element)
+    }
+    // 是否有环 Floyd 判环
+    // Box 独占所有权保证 Linked 不会成环，结果总是 false；任意后继关系的判环见 floyd
+    pub fn has_cycle(&self) -> bool {
+        self.cycle_start().is_some()
+    }
+    // 环入口的下标 Linked 不会成环，结果总是 None
+    pub fn cycle_start(&self) -> Option<usize> {
+        let head = ByAddress(self.head.as_deref()?);
+        floyd(head, |ByAddress(n)| n.next.as_deref().map(ByAddress)).map(|(mu, _)| mu)
+    }
+    // 是否回文 快慢指针找到前半部分的最后一个节点，反转后半部分逐个比较，比较完再恢复
+    pub fn is_palindrome(&mut self) -> bool where T: PartialEq {
+        let half = match self.head.as_deref() {
+            Some(head) => {
+                let mut slow = head;
+                let mut fast = head;
+                let mut half = 0;
+                while let Some(next) = fast.next.as_deref().and_then(|f| f.next.as_deref()) {
+                    slow = match slow.next.as_deref() {
+                        Some(s) => s,
+                        None => break,
+                    };
+                    fast = next;
+                    half += 1;
+                }
+                half
+            }
+            None => return true,
+        };
+        let mut c = self.cursor_mut();
+        for _ in 0..half {
+            c.move_next();
+        }
+        let mut rest = c.split_after();
+        rest.reverse();
+        let same = self.iter().zip(rest.iter()).all(|(a, b)| a == b);
+        rest.reverse();
+        let mut c = self.cursor_mut();
+        for _ in 0..half {
+            c.move_next();
+        }
+        c.splice_after(rest);
+        same
+    }
+    // 每 k 个节点一组反转 最后不足 k 个的保持原样
+    pub fn reverse_k_group(&mut self, k: usize) {
+        if k < 2 {
+            return;
+        }
+        let mut rest = self.head.take();
+        let mut tail = &mut self.head;
+        loop {
+            // 剩余节点不足 k 个直接接上
+            let mut count = 0;
+            let mut node = rest.as_deref();
+            while let Some(n) = node {
+                if count == k {
+                    break;
+                }
+                count += 1;
+                node = n.next.as_deref();
+            }
+            if count < k {
+                *tail = rest;
+                break;
+            }
+            // 依次头插得到反转的一组
+            let mut group = None;
+            for _ in 0..k {
+                if let Some(mut n) = rest {
+                    rest = n.next.take();
+                    n.next = group;
+                    group = Some(n);
+                }
+            }
+            *tail = group;
+            while let Some(n) = tail {
+                tail = &mut n.next;
+            }
+        }
+    }
+    // 向右旋转 k 位 后 k 个节点整体移动到 head
+    pub fn rotate(&mut self, k: usize) {
+        if self.len == 0 {
+            return;
+        }
+        let k = k % self.len;
+        if k == 0 {
+            return;
+        }
+        let rest = match self.head.as_mut().and_then(|h| h.search(0, self.len - k - 1)) {
+            Some(node) => node.next.take(),
+            None => return,
+        };
+        if let Some(mut rest) = rest {
+            rest.last().next = self.head.take();
+            self.head = Some(rest);
+        }
+    }
+    // 有序链表去重 只保留连续相同元素中的第一个
+    pub fn dedup_sorted(&mut self) where T: PartialEq {
+        let mut node = self.head.as_deref_mut();
+        while let Some(n) = node {
+            while let Some(mut dup) = n.next.take() {
+                if dup.element == n.element {
+                    n.next = dup.next.take();
+                    self.len -= 1;
+                } else {
+                    n.next = Some(dup);
+                    break;
+                }
+            }
+            node = n.next.as_deref_mut();
+        }
+    }
+    // 小于 pivot 的节点移动到前面，两部分内部保持原有顺序，返回小于 pivot 的节点个数
+    pub fn partition_around(&mut self, pivot: &T) -> usize where T: PartialOrd {
+        let mut less = None;
+        let mut less_tail = &mut less;
+        let mut rest = None;
+        let mut rest_tail = &mut rest;
+        let mut count = 0;
+        let mut node = self.head.take();
+        while let Some(mut n) = node {
+            node = n.next.take();
+            if n.element < *pivot {
+                less_tail = &mut less_tail.insert(n).next;
+                count += 1;
+            } else {
+                rest_tail = &mut rest_tail.insert(n).next;
+            }
+        }
+        *less_tail = rest;
+        self.head = less;
+        count
+    }
+}
+
+// 排序 只修改节点的 next 指向 不复制元素
+impl<T> Linked<T> {
+    // 稳定排序
+    pub fn sort(&mut self) where T: Ord {
+        self.sort_by(T::cmp);
+    }
+    // 按比较函数稳定排序 自底向上归并 O(nlogn)
+    // runs[i] 为空或者保存长度 2^i 的有序段，每取下一个节点像二进制加一一样向上合并进位
+    // 下标越大的段越早进入，合并时放在前面 保持稳定
+    pub fn sort_by<F>(&mut self, mut cmp: F) where F: FnMut(&T, &T) -> Ordering {
+        let mut runs: Vec<Option<Box<Node<T>>>> = Vec::new();
+        let mut node = self.head.take();
+        while let Some(mut n) = node {
+            node = n.next.take();
+            let mut carry = Some(n);
+            let mut i = 0;
+            loop {
+                if i == runs.len() {
+                    runs.push(carry);
+                    break;
+                }
+                match runs[i].take() {
+                    Some(run) => {
+                        carry = Node::merge_by(Some(run), carry, &mut cmp);
+                        i += 1;
+                    }
+                    None => {
+                        runs[i] = carry;
+                        break;
+                    }
+                }
+            }
+        }
+        let mut sorted = None;
+        for run in runs {
+            sorted = Node::merge_by(run, sorted, &mut cmp);
+        }
+        self.head = sorted;
+    }
+    // 合并多个有序链表 小顶堆中保存每个链表当前的 head 节点
+    // 每次取出最小节点接到结果末尾，再把它的下一个节点放回堆中 O(nlogk)
+    // 元素相等时先取靠前的链表 保持稳定
+    pub fn merge_k<I>(lists: I) -> Linked<T> where I: IntoIterator<Item = Linked<T>>, T: Ord {
+        let mut heap = BinaryHeap::new();
+        let mut len = 0;
+        for (i, mut l) in lists.into_iter().enumerate() {
+            len += l.len;
+            if let Some(node) = l.head.take() {
+                heap.push(HeapNode(node, i));
+            }
+        }
+        let mut head = None;
+        let mut tail = &mut head;
+        while let Some(HeapNode(mut node, i)) = heap.pop() {
+            if let Some(next) = node.next.take() {
+                heap.push(HeapNode(next, i));
+            }
+            tail = &mut tail.insert(node).next;
+        }
+        Linked { head, len }
+    }
+}
+
+// 堆中的节点和所属链表的序号，按元素反向比较使 BinaryHeap 成为小顶堆
+struct HeapNode<T>(Box<Node<T>>, usize);
+
+impl<T> PartialEq for HeapNode<T> where T: Ord {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for HeapNode<T> where T: Ord {}
+
+impl<T> PartialOrd for HeapNode<T> where T: Ord {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for HeapNode<T> where T: Ord {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.element.cmp(&self.0.element).then(other.1.cmp(&self.1))
+    }
+}
+
+// 拼接和拆分 只修改节点指向 不复制元素
+impl<T> Linked<T> {
+    // 把 other 的所有节点移动到末尾 other 变为空链表
+    pub fn append(&mut self, other: &mut Linked<T>) {
+        let mut tail = &mut self.head;
+        while let Some(node) = tail {
+            tail = &mut node.next;
+        }
+        *tail = other.head.take();
+        self.len += other.len;
+        other.len = 0;
+    }
+    // 从下标 at 处断开 返回 [at, len) 部分，at 超过长度返回 None
+    pub fn split_off(&mut self, at: usize) -> Option<Linked<T>> {
+        if at > self.len {
+            return None;
+        }
+        let mut c = self.cursor_mut();
+        for _ in 0..at {
+            c.move_next();
+        }
+        Some(c.split_before())
+    }
+    // 从第一个等于 value 的节点处断开 返回从该节点开始的部分，不存在返回 None
+    pub fn split_at_value(&mut self, value: &T) -> Option<Linked<T>> where T: PartialEq {
+        let mut c = self.cursor_mut();
+        loop {
+            match c.current() {
+                Some(x) if x == value => return Some(c.split_before()),
+                Some(_) => c.move_next(),
+                None => return None,
+            };
+        }
+    }
+    // 只保留前 len 个节点
+    pub fn truncate(&mut self, len: usize) {
+        self.split_off(len);
+    }
+    // 只保留满足条件的节点
+    pub fn retain<F>(&mut self, mut f: F) where F: FnMut(&T) -> bool {
+        let mut c = self.cursor_mut();
+        while let Some(x) = c.current() {
+            if f(x) {
+                c.move_next();
+            } else {
+                c.remove_current();
+            }
+        }
+    }
+    // 删除下标范围内的节点 返回按值迭代被删除的元素，超出长度的部分忽略
+    pub fn drain<R>(&mut self, range: R) -> IntoIter<T> where R: RangeBounds<usize> {
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e.saturating_add(1),
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.len,
+        }.min(self.len);
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s.saturating_add(1),
+            Bound::Unbounded => 0,
+        }.min(end);
+        let mut c = self.cursor_mut();
+        for _ in 0..start {
+            c.move_next();
+        }
+        let mut drained = c.split_before();
+        let rest = drained.split_off(end - start).unwrap_or_default();
+        c.splice_after(rest);
+        drained.into_iter()
+    }
+    // 迭代时删除并返回满足条件的节点，迭代器提前释放时剩余节点保留
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, T, F> where F: FnMut(&mut T) -> bool {
+        ExtractIf {
+            cursor: self.cursor_mut(),
+            pred,
+        }
+    }
+}
+
+// extract_if 返回的迭代器
+pub struct ExtractIf<'a, T, F> where F: FnMut(&mut T) -> bool {
+    cursor: CursorMut<'a, T>,
+    pred: F,
+}
+
+impl<T, F> Iterator for ExtractIf<'_, T, F> where F: FnMut(&mut T) -> bool {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let x = self.cursor.current()?;
+            if (self.pred)(x) {
+                return self.cursor.remove_current();
+            }
+            self.cursor.move_next();
+        }
+    }
+}
+
+// 按地址比较节点 判环时区分元素相同的不同节点
+struct ByAddress<'a, T>(&'a T);
+
+// 派生会要求 T: Copy
+impl<T> Clone for ByAddress<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ByAddress<'_, T> {}
+
+impl<T> PartialEq for ByAddress<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+// Floyd 判环（龟兔赛跑） next 返回后继，没有后继时为 None
+// 用于任意后继关系（下标数组、迭代函数等），Linked::has_cycle 也基于它
+// 有环时返回 (从 start 到环入口的步数, 环长度)，只使用 O(1) 额外空间
+pub fn floyd<S, F>(start: S, next: F) -> Option<(usize, usize)> where S: Copy + PartialEq, F: Fn(S) -> Option<S> {
+    // 快指针每次走 2 步 慢指针走 1 步，有环则一定在环内相遇
+    let mut slow = start;
+    let mut fast = start;
+    loop {
+        slow = next(slow)?;
+        fast = next(next(fast)?)?;
+        if slow == fast {
+            break;
+        }
+    }
+    // 慢指针回到起点 两个指针每次都走 1 步，再次相遇处为环入口
+    let mut mu = 0;
+    slow = start;
+    while slow != fast {
+        slow = next(slow)?;
+        fast = next(fast)?;
+        mu += 1;
+    }
+    // 从入口绕环一圈得到环长度
+    let mut lambda = 1;
+    fast = next(slow)?;
+    while slow != fast {
+        fast = next(fast)?;
+        lambda += 1;
+    }
+    Some((mu, lambda))
+}
+
+// 双向链表节点 prev/next 为相邻节点在 nodes 中的下标
+#[derive(Clone, Debug)]
+struct DoublyNode<T> {
+    element: T,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+// 双向链表 记录 head 和 tail，两端压入弹出都是 O(1)
+// 节点存放在 Vec 中通过下标互相指向，删除后的空位复用（避免 Rc<RefCell> 和裸指针）
+#[derive(Clone, Debug)]
+pub struct DoublyLinked<T> {
+    nodes: Vec<Option<DoublyNode<T>>>,
+    free: Vec<usize>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    len: usize,
+}
+
+impl<T> Default for DoublyLinked<T> {
+    fn default() -> Self {
+        DoublyLinked::new()
+    }
+}
+
+// Vec => DoublyLinked 转换
+impl<T> From<Vec<T>> for DoublyLinked<T> {
+    fn from(v: Vec<T>) -> Self {
+        v.into_iter().collect()
+    }
+}
+
+// DoublyLinked => Vec 转换
+impl<T> From<DoublyLinked<T>> for Vec<T> {
+    fn from(l: DoublyLinked<T>) -> Self {
+        let mut v = Vec::with_capacity(l.len);
+        v.extend(l);
+        v
+    }
+}
+
+// 双向迭代器 head 和 tail 两端同时向中间移动，剩余个数为 0 时结束
+pub struct DoublyIter<'a, T> {
+    list: &'a DoublyLinked<T>,
+    front: Option<usize>,
+    back: Option<usize>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for DoublyIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.list.nodes[self.front?].as_ref()?;
+        self.front = node.next;
+        self.len -= 1;
+        Some(&node.element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for DoublyIter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.list.nodes[self.back?].as_ref()?;
+        self.back = node.prev;
+        self.len -= 1;
+        Some(&node.element)
+    }
+}
+
+impl<T> ExactSizeIterator for DoublyIter<'_, T> {}
+
+// 按值双向迭代
+pub struct DoublyIntoIter<T>(DoublyLinked<T>);
+
+impl<T> Iterator for DoublyIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for DoublyIntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for DoublyIntoIter<T> {}
+
+impl<T> IntoIterator for DoublyLinked<T> {
+    type Item = T;
+    type IntoIter = DoublyIntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        DoublyIntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a DoublyLinked<T> {
+    type Item = &'a T;
+    type IntoIter = DoublyIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> FromIterator<T> for DoublyLinked<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut l = DoublyLinked::new();
+        l.extend(iter);
+        l
+    }
+}
+
+impl<T> Extend<T> for DoublyLinked<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.push_back(x);
+        }
+    }
+}
+
+// 实现 Queue 特性 tail 入队 head 出队
+impl<T> QueueCache<T> for DoublyLinked<T> {
+    fn new() -> Self {
+        DoublyLinked::new()
+    }
+
+    fn enqueue(&mut self, n: T) {
+        self.push_back(n);
+    }
+
+    fn dequeue(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+}
+
+// 实现 Cache Trait 作为 LRU 得存储结构支持，淘汰 tail 为 O(1)
+impl<T> Cache<T> for DoublyLinked<T> where T: PartialEq {
+    fn new() -> Self where Self: Sized {
+        DoublyLinked::new()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn contains(&mut self, n: &T) -> bool {
+        DoublyLinked::contains(self, n)
+    }
+
+    fn remove(&mut self, i: usize) -> Result<T, CacheError> {
+        let len = self.len;
+        self.remove(i).ok_or(CacheError::OutOfBounds { index: i, len })
+    }
+
+    fn insert(&mut self, i: usize, n: T) -> Result<(), CacheError> {
+        if i > self.len {
+            return Err(CacheError::OutOfBounds { index: i, len: self.len });
+        }
+        self.insert(i, n);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<T, CacheError> {
+        self.pop_back().ok_or(CacheError::Empty)
+    }
+
+    fn index(&mut self, i: usize) -> Result<&T, CacheError> {
+        let len = self.len;
+        DoublyLinked::index(self, i).ok_or(CacheError::OutOfBounds { index: i, len })
+    }
+}
+
+impl<T> DoublyLinked<T> {
+    pub fn new() -> Self {
+        DoublyLinked {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            head: None,
+            tail: None,
+            len: 0,
+        }
+    }
+    // 获取长度
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn iter(&self) -> DoublyIter<'_, T> {
+        DoublyIter {
+            list: self,
+            front: self.head,
+            back: self.tail,
+            len: self.len,
+        }
+    }
+    // head 插入
+    pub fn push_front(&mut self, n: T) {
+        self.link(n, None, self.head);
+    }
+    // tail 插入
+    pub fn push_back(&mut self, n: T) {
+        self.link(n, self.tail, None);
+    }
+    // head 删除
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.unlink(self.head?)
+    }
+    // tail 删除
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.unlink(self.tail?)
+    }
+    pub fn front(&self) -> Option<&T> {
+        self.element(self.head?)
+    }
+    pub fn back(&self) -> Option<&T> {
+        self.element(self.tail?)
+    }
+    // 根据索引下标查找节点 从距离较近的一端开始
+    pub fn index(&self, i: usize) -> Option<&T> {
+        self.element(self.locate(i)?)
+    }
+    // 索引插入节点 索引等于长度时末尾插入，超过长度不插入
+    pub fn insert(&mut self, i: usize, n: T) {
+        if i == self.len {
+            self.push_back(n);
+        } else if let Some(next) = self.locate(i) {
+            let prev = self.nodes[next].as_ref().and_then(|x| x.prev);
+            self.link(n, prev, Some(next));
+        }
+    }
+    // 根据索引下标删除节点
+    pub fn remove(&mut self, i: usize) -> Option<T> {
+        let i = self.locate(i)?;
+        self.unlink(i)
+    }
+    // 是否包含
+    pub fn contains(&self, n: &T) -> bool where T: PartialEq {
+        let mut node = self.head;
+        while let Some(x) = node.and_then(|i| self.nodes[i].as_ref()) {
+            if &x.element == n {
+                return true;
+            }
+            node = x.next;
+        }
+        false
+    }
+    fn element(&self, i: usize) -> Option<&T> {
+        self.nodes[i].as_ref().map(|x| &x.element)
+    }
+    // 第 i 个节点在 nodes 中的下标
+    fn locate(&self, i: usize) -> Option<usize> {
+        if i >= self.len {
+            return None;
+        }
+        if i < self.len / 2 {
+            let mut node = self.head;
+            for _ in 0..i {
+                node = node.and_then(|n| self.nodes[n].as_ref()?.next);
+            }
+            node
+        } else {
+            let mut node = self.tail;
+            for _ in i + 1..self.len {
+                node = node.and_then(|n| self.nodes[n].as_ref()?.prev);
+            }
+            node
+        }
+    }
+    // 在 prev 和 next 之间插入新节点
+    fn link(&mut self, n: T, prev: Option<usize>, next: Option<usize>) {
+        let node = DoublyNode {
+            element: n,
+            prev,
+            next,
+        };
+        let i = match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = Some(node);
+                i
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        match prev.and_then(|p| self.nodes[p].as_mut()) {
+            Some(p) => p.next = Some(i),
+            None => self.head = Some(i),
+        }
+        match next.and_then(|n| self.nodes[n].as_mut()) {
+            Some(n) => n.prev = Some(i),
+            None => self.tail = Some(i),
+        }
+        self.len += 1;
+    }
+    // 摘下节点 prev <=> next 直接相连并释放位置
+    fn unlink(&mut self, i: usize) -> Option<T> {
+        let node = self.nodes[i].take()?;
+        match node.prev.and_then(|p| self.nodes[p].as_mut()) {
+            Some(p) => p.next = node.next,
+            None => self.head = node.next,
+        }
+        match node.next.and_then(|n| self.nodes[n].as_mut()) {
+            Some(n) => n.prev = node.prev,
+            None => self.tail = node.prev,
+        }
+        self.free.push(i);
+        self.len -= 1;
+        Some(node.element)
+    }
+}
+
+// 循环链表节点 next 为下一个节点在 nodes 中的下标，最后一个节点指回第一个
+#[derive(Clone, Debug)]
+struct CircularNode<T> {
+    element: T,
+    next: usize,
+}
+
+// 循环单链表 节点同样存放在 Vec 中通过下标指向
+// 记录当前节点和它的前一个节点，删除当前节点、在当前节点前插入都是 O(1)
+#[derive(Clone, Debug)]
+pub struct CircularLinked<T> {
+    nodes: Vec<Option<CircularNode<T>>>,
+    free: Vec<usize>,
+    current: Option<usize>,
+    prev: Option<usize>,
+    len: usize,
+}
+
+impl<T> Default for CircularLinked<T> {
+    fn default() -> Self {
+        CircularLinked::new()
+    }
+}
+
+impl<T> From<Vec<T>> for CircularLinked<T> {
+    fn from(v: Vec<T>) -> Self {
+        v.into_iter().collect()
+    }
+}
+
+impl<T> FromIterator<T> for CircularLinked<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut l = CircularLinked::new();
+        for x in iter {
+            l.push(x);
+        }
+        l
+    }
+}
+
+impl<T> CircularLinked<T> {
+    pub fn new() -> Self {
+        CircularLinked {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            current: None,
+            prev: None,
+            len: 0,
+        }
+    }
+    // 获取长度
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    // 在当前节点前面插入（转一圈最后访问到），空链表时成为当前节点
+    pub fn push(&mut self, n: T) {
+        let i = match (self.current, self.prev) {
+            (Some(cur), Some(prev)) => {
+                let i = self.alloc(n, cur);
+                if let Some(p) = self.nodes[prev].as_mut() {
+                    p.next = i;
+                }
+                i
+            }
+            _ => {
+                // 唯一的节点指向自己
+                let i = self.alloc(n, 0);
+                if let Some(x) = self.nodes[i].as_mut() {
+                    x.next = i;
+                }
+                self.current = Some(i);
+                i
+            }
+        };
+        self.prev = Some(i);
+        self.len += 1;
+    }
+    // 当前节点
+    pub fn current(&self) -> Option<&T> {
+        self.nodes[self.current?].as_ref().map(|x| &x.element)
+    }
+    pub fn current_mut(&mut self) -> Option<&mut T> {
+        self.nodes[self.current?].as_mut().map(|x| &mut x.element)
+    }
+    // 当前节点移动到下一个
+    pub fn rotate(&mut self) {
+        if let Some(next) = self.current.and_then(|c| self.nodes[c].as_ref()).map(|x| x.next) {
+            self.prev = self.current;
+            self.current = Some(next);
+        }
+    }
+    // 向前移动 k 个节点 超过长度时按 k % len 计算，返回新的当前节点
+    pub fn advance(&mut self, k: usize) -> Option<&mut T> {
+        if self.len > 0 {
+            for _ in 0..k % self.len {
+                self.rotate();
+            }
+        }
+        self.current_mut()
+    }
+    // 删除当前节点 下一个节点成为当前节点
+    pub fn remove_current(&mut self) -> Option<T> {
+        let cur = self.current?;
+        let node = self.nodes[cur].take()?;
+        self.free.push(cur);
+        self.len -= 1;
+        if self.len == 0 {
+            self.current = None;
+            self.prev = None;
+        } else {
+            if let Some(p) = self.prev.and_then(|p| self.nodes[p].as_mut()) {
+                p.next = node.next;
+            }
+            self.current = Some(node.next);
+        }
+        Some(node.element)
+    }
+    // 从当前节点开始转一圈
+    pub fn iter(&self) -> CircularIter<'_, T> {
+        CircularIter {
+            list: self,
+            next: self.current,
+            len: self.len,
+        }
+    }
+    fn alloc(&mut self, n: T, next: usize) -> usize {
+        let node = CircularNode {
+            element: n,
+            next,
+        };
+        match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = Some(node);
+                i
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        }
+    }
+}
+
+// 循环链表迭代器 从当前节点开始每个节点返回一次
+pub struct CircularIter<'a, T> {
+    list: &'a CircularLinked<T>,
+    next: Option<usize>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for CircularIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.list.nodes[self.next?].as_ref()?;
+        self.next = Some(node.next);
+        self.len -= 1;
+        Some(&node.element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for CircularIter<'_, T> {}
+
+// 约瑟夫问题 n 个人（编号 1 到 n）围成一圈，从 1 开始报数，报到 k 的人出列，下一个人重新从 1 开始
+// 返回出列顺序，最后一个即幸存者 O(nk)
+pub fn josephus(n: usize, k: usize) -> Vec<usize> {
+    let mut order = Vec::with_capacity(n);
+    if k == 0 {
+        return order;
+    }
+    let mut ring: CircularLinked<usize> = (1..=n).collect();
+    while !ring.is_empty() {
+        ring.advance(k - 1);
+        if let Some(x) = ring.remove_current() {
+            order.push(x);
+        }
+    }
+    order
+}
+
+// 轮询调度 每个任务是一个迭代器（依次产生时间片）
+// 每次调度取当前任务的下一个时间片然后轮到下一个任务，任务结束后移出，所有任务结束时迭代结束
+pub struct RoundRobin<I> where I: Iterator {
+    jobs: CircularLinked<I>,
+}
+
+impl<I> Default for RoundRobin<I> where I: Iterator {
+    fn default() -> Self {
+        RoundRobin::new()
+    }
+}
+
+impl<I> FromIterator<I> for RoundRobin<I> where I: Iterator {
+    fn from_iter<J: IntoIterator<Item = I>>(iter: J) -> Self {
+        RoundRobin {
+            jobs: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<I> RoundRobin<I> where I: Iterator {
+    pub fn new() -> Self {
+        RoundRobin {
+            jobs: CircularLinked::new(),
+        }
+    }
+    // 加入任务 排在当前一轮的最后
+    pub fn push(&mut self, job: I) {
+        self.jobs.push(job);
+    }
+    // 未结束的任务个数
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+}
+
+impl<I> Iterator for RoundRobin<I> where I: Iterator {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(job) = self.jobs.current_mut() {
+            match job.next() {
+                Some(x) => {
+                    self.jobs.rotate();
+                    return Some(x);
+                }
+                None => {
+                    self.jobs.remove_current();
+                }
+            }
+        }
+        None
+    }
+}
+
+// 空下标 相当于 None，用 u32::MAX 表示让节点更紧凑
+const NIL: u32 = u32::MAX;
+
+// arena 节点 element 为 None 时是空位，空位的 next 串成空闲链表
+#[derive(Clone, Debug)]
+struct ArenaNode<T> {
+    element: Option<T>,
+    next: u32,
+}
+
+// 基于 arena 的单链表，接口与 Linked 相同
+// 所有节点存放在同一个 Vec 中用 u32 下标串联，删除后的空位通过空闲链表复用，不需要每个元素单独分配
+// 额外记录 tail，末尾压入为 O(1)；节点不能跨 arena 共享，拼接和拆分出的部分需要逐个移动到另一个 arena，代价与移动的节点数成正比
+// 最多保存 u32::MAX - 1 个节点，已满时 try_push / try_insert 把元素原样返回
+#[derive(Clone)]
+pub struct ArenaLinked<T> {
+    nodes: Vec<ArenaNode<T>>,
+    head: u32,
+    tail: u32,
+    // 空闲链表的第一个空位
+    free: u32,
+    len: usize,
+}
+
+impl<T> Default for ArenaLinked<T> {
+    fn default() -> Self {
+        ArenaLinked::new()
+    }
+}
+
+impl<T> Debug for ArenaLinked<T> where T: Debug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// Vec => ArenaLinked 转换
+impl<T> From<Vec<T>> for ArenaLinked<T> {
+    fn from(v: Vec<T>) -> Self {
+        v.into_iter().collect()
+    }
+}
+
+// ArenaLinked => Vec 转换
+impl<T> From<ArenaLinked<T>> for Vec<T> {
+    fn from(l: ArenaLinked<T>) -> Self {
+        let mut v = Vec::with_capacity(l.len);
+        v.extend(l);
+        v
+    }
+}
+
+impl<T> FromIterator<T> for ArenaLinked<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut l = ArenaLinked::new();
+        l.extend(iter);
+        l
+    }
+}
+
+impl<T> Extend<T> for ArenaLinked<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.push(x);
+        }
+    }
+}
+
+// 实现 Queue 特性
+impl<T> QueueCache<T> for ArenaLinked<T> {
+    fn new() -> Self {
+        ArenaLinked::new()
+    }
+
+    fn enqueue(&mut self, n: T) {
+        self.push(n);
+    }
+
+    fn dequeue(&mut self) -> Option<T> {
+        self.unlink_after(NIL)
+    }
+}
+
+// 实现 Cache Trait 作为 LRU 得存储结构支持
+impl<T> Cache<T> for ArenaLinked<T> where T: PartialEq {
+    fn new() -> Self where Self: Sized {
+        ArenaLinked::new()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn contains(&mut self, n: &T) -> bool {
+        ArenaLinked::contains(self, n)
+    }
+
+    fn remove(&mut self, i: usize) -> Result<T, CacheError> {
+        let len = self.len;
+        self.remove(i).ok_or(CacheError::OutOfBounds { index: i, len })
+    }
+
+    fn insert(&mut self, i: usize, n: T) -> Result<(), CacheError> {
+        if i > self.len {
+            return Err(CacheError::OutOfBounds { index: i, len: self.len });
+        }
+        self.try_insert(i, n).map_err(|_| CacheError::Full)
+    }
+
+    fn pop(&mut self) -> Result<T, CacheError> {
+        self.pop().ok_or(CacheError::Empty)
+    }
+
+    fn index(&mut self, i: usize) -> Result<&T, CacheError> {
+        let len = self.len;
+        ArenaLinked::index(self, i).ok_or(CacheError::OutOfBounds { index: i, len })
+    }
+}
+
+impl<T> ArenaLinked<T> {
+    pub fn new() -> Self {
+        ArenaLinked {
+            nodes: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            free: NIL,
+            len: 0,
+        }
+    }
+    // 预先分配 n 个节点的空间
+    pub fn with_capacity(n: usize) -> Self {
+        let mut l = ArenaLinked::new();
+        l.nodes.reserve(n);
+        l
+    }
+    // 获取长度
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn iter(&self) -> ArenaIter<'_, T> {
+        ArenaIter {
+            list: self,
+            next: self.head,
+            len: self.len,
+        }
+    }
+    // 节点在 Vec 中不是按链表顺序存放，沿 next 下标直接访问，不额外分配
+    // 安全的借用拆分（split_at_mut）只能按存放顺序切分，链表顺序可以任意跳转，所以这里用裸指针，约束见 ArenaIterMut
+    pub fn iter_mut(&mut self) -> ArenaIterMut<'_, T> {
+        ArenaIterMut {
+            nodes: self.nodes.as_mut_ptr(),
+            slots: self.nodes.len(),
+            next: self.head,
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+    // 从 head 开始的可变游标
+    pub fn cursor_mut(&mut self) -> ArenaCursorMut<'_, T> {
+        ArenaCursorMut {
+            list: self,
+            prev: NIL,
+            index: 0,
+        }
+    }
+    // 反转
+    pub fn reverse(&mut self) {
+        let mut prev = NIL;
+        let mut cur = self.head;
+        while cur != NIL {
+            let next = self.next(cur);
+            self.nodes[cur as usize].next = prev;
+            prev = cur;
+            cur = next;
+        }
+        self.tail = self.head;
+        self.head = prev;
+    }
+    // 末尾压入节点 O(1)，arena 已满时 panic
+    pub fn push(&mut self, n: T) {
+        self.link_after(self.tail, n);
+    }
+    // 末尾压入节点 arena 已满时返回 Err 交还元素
+    pub fn try_push(&mut self, n: T) -> Result<(), T> {
+        self.try_link_after(self.tail, n).map(|_| ())
+    }
+    // 根据索引下标删除节点
+    pub fn remove(&mut self, i: usize) -> Option<T> {
+        if i >= self.len {
+            return None;
+        }
+        let prev = if i == 0 { NIL } else { self.locate(i - 1) };
+        self.unlink_after(prev)
+    }
+    // 索引插入节点 索引等于长度时末尾插入，超过长度不插入，arena 已满时 panic
+    // 与 Linked::insert 的区别：Linked 为空时任意索引都插入为 head，这里空链表只接受索引 0
+    pub fn insert(&mut self, i: usize, n: T) {
+        if i > self.len {
+            return;
+        }
+        let prev = if i == 0 { NIL } else { self.locate(i - 1) };
+        self.link_after(prev, n);
+    }
+    // 索引插入节点 超过长度或 arena 已满时返回 Err 交还元素
+    pub fn try_insert(&mut self, i: usize, n: T) -> Result<(), T> {
+        if i > self.len {
+            return Err(n);
+        }
+        let prev = if i == 0 { NIL } else { self.locate(i - 1) };
+        self.try_link_after(prev, n).map(|_| ())
+    }
+    // 末尾删除节点 单链表需要查找前一个节点
+    pub fn pop(&mut self) -> Option<T> {
+        self.remove(self.len.checked_sub(1)?)
+    }
+    // 是否包含
+    pub fn contains(&self, n: &T) -> bool where T: PartialEq {
+        self.iter().any(|x| x == n)
+    }
+    // 根据索引下标查找节点
+    pub fn index(&self, i: usize) -> Option<&T> {
+        if i >= self.len {
+            return None;
+        }
+        self.nodes[self.locate(i) as usize].element.as_ref()
+    }
+    // 合并有序列表 节点移动到新的 arena
+    pub fn merge_order(n: ArenaLinked<T>, m: ArenaLinked<T>) -> Option<ArenaLinked<T>> where T: PartialOrd {
+        if n.is_empty() && m.is_empty() {
+            return None;
+        }
+        let mut l = ArenaLinked::with_capacity(n.len + m.len);
+        let mut n = n.into_iter().peekable();
+        let mut m = m.into_iter().peekable();
+        loop {
+            let take_m = match (n.peek(), m.peek()) {
+                (Some(x), Some(y)) => x > y,
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (None, None) => break,
+            };
+            let next = if take_m { m.next() } else { n.next() };
+            l.extend(next);
+        }
+        Some(l)
+    }
+    fn next(&self, i: u32) -> u32 {
+        self.nodes[i as usize].next
+    }
+    // 第 i 个节点的下标 调用方保证 i < len
+    fn locate(&self, i: usize) -> u32 {
+        if i + 1 == self.len {
+            return self.tail;
+        }
+        let mut node = self.head;
+        for _ in 0..i {
+            node = self.next(node);
+        }
+        node
+    }
+    // 占用一个空位 优先复用空闲链表，下标用完时返回 Err 交还元素
+    fn alloc(&mut self, n: T) -> Result<u32, T> {
+        if self.free != NIL {
+            let i = self.free;
+            self.free = self.next(i);
+            self.nodes[i as usize] = ArenaNode {
+                element: Some(n),
+                next: NIL,
+            };
+            return Ok(i);
+        }
+        if self.nodes.len() >= NIL as usize {
+            return Err(n);
+        }
+        self.nodes.push(ArenaNode {
+            element: Some(n),
+            next: NIL,
+        });
+        Ok((self.nodes.len() - 1) as u32)
+    }
+    // 释放位置 放回空闲链表
+    fn release(&mut self, i: u32) -> Option<T> {
+        let node = &mut self.nodes[i as usize];
+        node.next = self.free;
+        self.free = i;
+        node.element.take()
+    }
+    // 在 prev 后面插入新节点 arena 已满时 panic，用于无法返回错误的接口（Extend、QueueCache 等）
+    fn link_after(&mut self, prev: u32, n: T) -> u32 {
+        match self.try_link_after(prev, n) {
+            Ok(i) => i,
+            Err(_) => panic!("ArenaLinked is full: at most {} nodes", NIL - 1),
+        }
+    }
+    // 在 prev 后面插入新节点，prev 为 NIL 时作为 head，返回新节点下标
+    fn try_link_after(&mut self, prev: u32, n: T) -> Result<u32, T> {
+        let i = self.alloc(n)?;
+        let next = if prev == NIL { self.head } else { self.next(prev) };
+        self.nodes[i as usize].next = next;
+        if prev == NIL {
+            self.head = i;
+        } else {
+            self.nodes[prev as usize].next = i;
+        }
+        if next == NIL {
+            self.tail = i;
+        }
+        self.len += 1;
+        Ok(i)
+    }
+    // 删除 prev 后面的节点，prev 为 NIL 时删除 head
+    fn unlink_after(&mut self, prev: u32) -> Option<T> {
+        let cur = if prev == NIL { self.head } else { self.next(prev) };
+        if cur == NIL {
+            return None;
+        }
+        let next = self.next(cur);
+        if prev == NIL {
+            self.head = next;
+        } else {
+            self.nodes[prev as usize].next = next;
+        }
+        if self.tail == cur {
+            self.tail = prev;
+        }
+        self.len -= 1;
+        self.release(cur)
+    }
+    // 从 start 开始到末尾的节点已经从链表断开，逐个移动到新的 arena O(m)，m 为断开的节点数
+    // 下标只在本 arena 内有效，不能直接把节点链接到另一个 arena
+    fn detach(&mut self, start: u32) -> ArenaLinked<T> {
+        let mut rest = ArenaLinked::new();
+        let mut i = start;
+        while i != NIL {
+            let next = self.next(i);
+            if let Some(x) = self.release(i) {
+                rest.push(x);
+            }
+            i = next;
+        }
+        self.len -= rest.len;
+        rest
+    }
+    // 重新链接后从 head 查找 tail
+    fn fix_tail(&mut self) {
+        let mut i = self.head;
+        self.tail = NIL;
+        while i != NIL {
+            self.tail = i;
+            i = self.next(i);
+        }
+    }
+}
+
+// 经典链表算法 arena 中快慢指针都是下标，可以同时移动
+impl<T> ArenaLinked<T> {
+    // 中间节点 偶数个取后一个
+    pub fn middle(&self) -> Option<&T> {
+        if self.head == NIL {
+            return None;
+        }
+        let mut slow = self.head;
+        let mut fast = self.head;
+        while fast != NIL && self.next(fast) != NIL {
+            slow = self.next(slow);
+            fast = self.next(self.next(fast));
+        }
+        self.nodes[slow as usize].element.as_ref()
+    }
+    // 删除倒数第 n 个节点（n 从 1 开始） 快指针先走 n 步然后快慢指针一起走，一次遍历
+    pub fn remove_nth_from_end(&mut self, n: usize) -> Option<T> {
+        if n == 0 {
+            return None;
+        }
+        let mut fast = self.head;
+        for _ in 0..n {
+            if fast == NIL {
+                return None;
+            }
+            fast = self.next(fast);
+        }
+        let mut prev = NIL;
+        let mut slow = self.head;
+        while fast != NIL {
+            fast = self.next(fast);
+            prev = slow;
+            slow = self.next(slow);
+        }
+        self.unlink_after(prev)
+    }
+    // 是否回文 反转后半部分逐个比较 比较完再恢复
+    pub fn is_palindrome(&mut self) -> bool where T: PartialEq {
+        if self.head == NIL {
+            return true;
+        }
+        // 前半部分的最后一个节点
+        let mut slow = self.head;
+        let mut fast = self.head;
+        while self.next(fast) != NIL && self.next(self.next(fast)) != NIL {
+            slow = self.next(slow);
+            fast = self.next(self.next(fast));
+        }
+        let second = self.reverse_chain(self.next(slow));
+        let (mut a, mut b) = (self.head, second);
+        let mut same = true;
+        while b != NIL {
+            if self.nodes[a as usize].element != self.nodes[b as usize].element {
+                same = false;
+                break;
+            }
+            a = self.next(a);
+            b = self.next(b);
+        }
+        let second = self.reverse_chain(second);
+        self.nodes[slow as usize].next = second;
+        same
+    }
+    // 每 k 个节点一组反转 最后不足 k 个的保持原样
+    pub fn reverse_k_group(&mut self, k: usize) {
+        if k < 2 {
+            return;
+        }
+        // prev 为上一组的最后一个节点
+        let mut prev = NIL;
+        loop {
+            let start = if prev == NIL { self.head } else { self.next(prev) };
+            let mut end = start;
+            let mut count = 0;
+            while end != NIL && count < k {
+                end = self.next(end);
+                count += 1;
+            }
+            if count < k {
+                break;
+            }
+            // 反转 [start, end) 反转后 start 成为这一组的最后一个节点
+            let mut p = end;
+            let mut cur = start;
+            while cur != end {
+                let next = self.next(cur);
+                self.nodes[cur as usize].next = p;
+                p = cur;
+                cur = next;
+            }
+            if prev == NIL {
+                self.head = p;
+            } else {
+                self.nodes[prev as usize].next = p;
+            }
+            prev = start;
+        }
+        self.fix_tail();
+    }
+    // 向右旋转 k 位 后 k 个节点整体移动到 head
+    pub fn rotate(&mut self, k: usize) {
+        if self.len == 0 {
+            return;
+        }
+        let k = k % self.len;
+        if k == 0 {
+            return;
+        }
+        let new_tail = self.locate(self.len - k - 1);
+        let new_head = self.next(new_tail);
+        self.nodes[self.tail as usize].next = self.head;
+        self.nodes[new_tail as usize].next = NIL;
+        self.head = new_head;
+        self.tail = new_tail;
+    }
+    // 有序链表去重 只保留连续相同元素中的第一个
+    pub fn dedup_sorted(&mut self) where T: PartialEq {
+        let mut i = self.head;
+        while i != NIL {
+            let next = self.next(i);
+            if next != NIL && self.nodes[next as usize].element == self.nodes[i as usize].element {
+                self.unlink_after(i);
+            } else {
+                i = next;
+            }
+        }
+    }
+    // 小于 pivot 的节点移动到前面，两部分内部保持原有顺序，返回小于 pivot 的节点个数
+    pub fn partition_around(&mut self, pivot: &T) -> usize where T: PartialOrd {
+        let (mut less, mut less_tail) = (NIL, NIL);
+        let (mut rest, mut rest_tail) = (NIL, NIL);
+        let mut count = 0;
+        let mut i = self.head;
+        while i != NIL {
+            let next = self.next(i);
+            self.nodes[i as usize].next = NIL;
+            let is_less = matches!(self.nodes[i as usize].element, Some(ref x) if x < pivot);
+            let (head, tail) = if is_less { (&mut less, &mut less_tail) } else { (&mut rest, &mut rest_tail) };
+            if *tail == NIL {
+                *head = i;
+            } else {
+                self.nodes[*tail as usize].next = i;
+            }
+            *tail = i;
+            if is_less {
+                count += 1;
+            }
+            i = next;
+        }
+        if less == NIL {
+            self.head = rest;
+            self.tail = rest_tail;
+        } else {
+            self.nodes[less_tail as usize].next = rest;
+            self.head = less;
+            self.tail = if rest == NIL { less_tail } else { rest_tail };
+        }
+        count
+    }
+    // 反转从 i 开始到末尾的节点 返回新的第一个节点
+    fn reverse_chain(&mut self, mut i: u32) -> u32 {
+        let mut prev = NIL;
+        while i != NIL {
+            let next = self.next(i);
+            self.nodes[i as usize].next = prev;
+            prev = i;
+            i = next;
+        }
+        prev
+    }
+}
+
+// 排序 只修改节点的 next 下标 不移动元素
+impl<T> ArenaLinked<T> {
+    // 稳定排序
+    pub fn sort(&mut self) where T: Ord {
+        self.sort_by(T::cmp);
+    }
+    // 按比较函数稳定排序 与 Linked 相同的自底向上归并
+    pub fn sort_by<F>(&mut self, mut cmp: F) where F: FnMut(&T, &T) -> Ordering {
+        let mut runs: Vec<u32> = Vec::new();
+        let mut i = self.head;
+        while i != NIL {
+            let next = self.next(i);
+            self.nodes[i as usize].next = NIL;
+            let mut carry = i;
+            let mut k = 0;
+            loop {
+                if k == runs.len() {
+                    runs.push(carry);
+                    break;
+                }
+                if runs[k] == NIL {
+                    runs[k] = carry;
+                    break;
+                }
+                carry = self.merge_chains(runs[k], carry, &mut cmp);
+                runs[k] = NIL;
+                k += 1;
+            }
+            i = next;
+        }
+        let mut sorted = NIL;
+        for run in runs {
+            sorted = self.merge_chains(run, sorted, &mut cmp);
+        }
+        self.head = sorted;
+        self.fix_tail();
+    }
+    // 合并多个有序链表 小顶堆中保存每个链表当前的第一个元素 O(nlogk)
+    pub fn merge_k<I>(lists: I) -> ArenaLinked<T> where I: IntoIterator<Item = ArenaLinked<T>>, T: Ord {
+        let mut iters: Vec<ArenaIntoIter<T>> = lists.into_iter().map(|l| l.into_iter()).collect();
+        let mut l = ArenaLinked::with_capacity(iters.iter().map(|it| it.len()).sum());
+        let mut heap = BinaryHeap::new();
+        for (i, it) in iters.iter_mut().enumerate() {
+            if let Some(x) = it.next() {
+                heap.push(HeapEntry(x, i));
+            }
+        }
+        while let Some(HeapEntry(x, i)) = heap.pop() {
+            if let Some(next) = iters[i].next() {
+                heap.push(HeapEntry(next, i));
+            }
+            l.push(x);
+        }
+        l
+    }
+    // 合并两条以 NIL 结尾的有序节点链 相等时先取 a
+    fn merge_chains<F>(&mut self, mut a: u32, mut b: u32, cmp: &mut F) -> u32 where F: FnMut(&T, &T) -> Ordering {
+        let mut head = NIL;
+        let mut tail = NIL;
+        while a != NIL && b != NIL {
+            let take_b = match (&self.nodes[a as usize].element, &self.nodes[b as usize].element) {
+                (Some(x), Some(y)) => cmp(x, y) == Ordering::Greater,
+                _ => false,
+            };
+            let next = if take_b { b } else { a };
+            if take_b {
+                b = self.next(b);
+            } else {
+                a = self.next(a);
+            }
+            if tail == NIL {
+                head = next;
+            } else {
+                self.nodes[tail as usize].next = next;
+            }
+            tail = next;
+        }
+        let rest = if a != NIL { a } else { b };
+        if tail == NIL {
+            return rest;
+        }
+        self.nodes[tail as usize].next = rest;
+        head
+    }
+}
+
+// 堆中的元素和所属链表的序号，按元素反向比较使 BinaryHeap 成为小顶堆
+struct HeapEntry<T>(T, usize);
+
+impl<T> PartialEq for HeapEntry<T> where T: Ord {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for HeapEntry<T> where T: Ord {}
+
+impl<T> PartialOrd for HeapEntry<T> where T: Ord {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for HeapEntry<T> where T: Ord {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.cmp(&self.0).then(other.1.cmp(&self.1))
+    }
+}
+
+// 拼接和拆分
+impl<T> ArenaLinked<T> {
+    // 把 other 的所有元素移动到末尾 other 变为空链表，需要移动到当前 arena O(m)
+    pub fn append(&mut self, other: &mut ArenaLinked<T>) {
+        self.extend(mem::take(other));
+    }
+    // 从下标 at 处断开 返回 [at, len) 部分，at 超过长度返回 None；拆出的节点移动到新的 arena O(len - at)
+    pub fn split_off(&mut self, at: usize) -> Option<ArenaLinked<T>> {
+        if at > self.len {
+            return None;
+        }
+        let mut c = self.cursor_mut();
+        for _ in 0..at {
+            c.move_next();
+        }
+        Some(c.split_before())
+    }
+    // 从第一个等于 value 的节点处断开 返回从该节点开始的部分，不存在返回 None；拆出的节点移动到新的 arena
+    pub fn split_at_value(&mut self, value: &T) -> Option<ArenaLinked<T>> where T: PartialEq {
+        let mut c = self.cursor_mut();
+        loop {
+            match c.current() {
+                Some(x) if x == value => return Some(c.split_before()),
+                Some(_) => c.move_next(),
+                None => return None,
+            };
+        }
+    }
+    // 只保留前 len 个节点
+    pub fn truncate(&mut self, len: usize) {
+        self.split_off(len);
+    }
+    // 只保留满足条件的节点
+    pub fn retain<F>(&mut self, mut f: F) where F: FnMut(&T) -> bool {
+        let mut c = self.cursor_mut();
+        while let Some(x) = c.current() {
+            if f(x) {
+                c.move_next();
+            } else {
+                c.remove_current();
+            }
+        }
+    }
+    // 删除下标范围内的节点 返回按值迭代被删除的元素，超出长度的部分忽略；被删除的节点移动到新的 arena
+    pub fn drain<R>(&mut self, range: R) -> ArenaIntoIter<T> where R: RangeBounds<usize> {
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e.saturating_add(1),
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.len,
+        }.min(self.len);
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s.saturating_add(1),
+            Bound::Unbounded => 0,
+        }.min(end);
+        let mut drained = ArenaLinked::with_capacity(end - start);
+        let mut c = self.cursor_mut();
+        for _ in 0..start {
+            c.move_next();
+        }
+        for _ in start..end {
+            drained.extend(c.remove_current());
+        }
+        drained.into_iter()
+    }
+    // 迭代时删除并返回满足条件的节点，迭代器提前释放时剩余节点保留
+    pub fn extract_if<F>(&mut self, pred: F) -> ArenaExtractIf<'_, T, F> where F: FnMut(&mut T) -> bool {
+        ArenaExtractIf {
+            cursor: self.cursor_mut(),
+            pred,
+        }
+    }
+}
+
+// 可变游标 prev 为当前节点的前一个节点，为 NIL 时当前节点是 head
+pub struct ArenaCursorMut<'a, T> {
+    list: &'a mut ArenaLinked<T>,
+    prev: u32,
+    index: usize,
+}
+
+impl<T> ArenaCursorMut<'_, T> {
+    // 当前下标 在末尾之后时等于长度
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    // 当前元素 可直接修改
+    pub fn current(&mut self) -> Option<&mut T> {
+        let cur = self.cur();
+        if cur == NIL {
+            return None;
+        }
+        self.list.nodes[cur as usize].element.as_mut()
+    }
+    // 下一个元素
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        let cur = self.cur();
+        if cur == NIL || self.list.next(cur) == NIL {
+            return None;
+        }
+        let next = self.list.next(cur);
+        self.list.nodes[next as usize].element.as_mut()
+    }
+    // 向后移动一个节点 已经在末尾之后时返回 false
+    pub fn move_next(&mut self) -> bool {
+        let cur = self.cur();
+        if cur == NIL {
+            return false;
+        }
+        self.prev = cur;
+        self.index += 1;
+        true
+    }
+    // 在当前节点后面插入，在末尾之后时插入到末尾并成为当前节点
+    pub fn insert_after(&mut self, n: T) {
+        let cur = self.cur();
+        let at = if cur == NIL { self.prev } else { cur };
+        self.list.link_after(at, n);
+    }
+    // 删除当前节点 游标指向原来的下一个节点
+    pub fn remove_current(&mut self) -> Option<T> {
+        self.list.unlink_after(self.prev)
+    }
+    // 从当前节点后面断开 返回后半部分，后半部分移动到新的 arena O(m)
+    pub fn split_after(&mut self) -> ArenaLinked<T> {
+        let cur = self.cur();
+        if cur == NIL {
+            return ArenaLinked::new();
+        }
+        let rest = self.list.next(cur);
+        self.list.nodes[cur as usize].next = NIL;
+        self.list.tail = cur;
+        self.list.detach(rest)
+    }
+    // 从当前节点前面断开 返回从当前节点开始的部分，游标停在末尾之后，断开的部分移动到新的 arena O(m)
+    pub fn split_before(&mut self) -> ArenaLinked<T> {
+        let cur = self.cur();
+        if self.prev == NIL {
+            self.list.head = NIL;
+        } else {
+            self.list.nodes[self.prev as usize].next = NIL;
+        }
+        self.list.tail = self.prev;
+        self.list.detach(cur)
+    }
+    // 把另一个链表的元素插入到当前节点后面，在末尾之后时接到末尾 O(m)
+    pub fn splice_after(&mut self, other: ArenaLinked<T>) {
+        let cur = self.cur();
+        let mut at = if cur == NIL { self.prev } else { cur };
+        for x in other {
+            at = self.list.link_after(at, x);
+        }
+    }
+    fn cur(&self) -> u32 {
+        if self.prev == NIL {
+            self.list.head
+        } else {
+            self.list.next(self.prev)
+        }
+    }
+}
+
+// extract_if 返回的迭代器
+pub struct ArenaExtractIf<'a, T, F> where F: FnMut(&mut T) -> bool {
+    cursor: ArenaCursorMut<'a, T>,
+    pred: F,
+}
+
+impl<T, F> Iterator for ArenaExtractIf<'_, T, F> where F: FnMut(&mut T) -> bool {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let x = self.cursor.current()?;
+            if (self.pred)(x) {
+                return self.cursor.remove_current();
+            }
+            self.cursor.move_next();
+        }
+    }
+}
+
+// 迭代器 从 head 到 tail 依次返回元素引用
+pub struct ArenaIter<'a, T> {
+    list: &'a ArenaLinked<T>,
+    next: u32,
+    len: usize,
+}
+
+impl<'a, T> Iterator for ArenaIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == NIL {
+            return None;
+        }
+        let node = &self.list.nodes[self.next as usize];
+        self.next = node.next;
+        self.len -= 1;
+        node.element.as_ref()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for ArenaIter<'_, T> {}
+
+// 可变迭代器 沿 next 下标逐个返回元素，相当于持有 &'a mut [ArenaNode<T>]
+// unsafe 代码依赖的约束：
+// 1. nodes 和 slots 来自 &'a mut ArenaLinked 的 nodes.as_mut_ptr() 和 nodes.len()，'a 期间链表被独占借用，Vec 不会被访问、修改或重新分配
+// 2. ArenaLinked 的所有修改操作保证从 head 沿 next 走 len 步经过 len 个互不相同的下标（不会成环，空闲链表和链表不相交）
+// 所以迭代器最多走 len 步，每个下标只访问一次，返回的可变引用互不重叠；下标是否越界在访问前检查
+pub struct ArenaIterMut<'a, T> {
+    nodes: *mut ArenaNode<T>,
+    slots: usize,
+    next: u32,
+    len: usize,
+    marker: PhantomData<&'a mut [ArenaNode<T>]>,
+}
+
+// SAFETY: 迭代器只是 &'a mut [ArenaNode<T>] 的另一种访问方式，按 &mut [ArenaNode<T>] 相同的条件实现 Send 和 Sync；
+// 共享 &ArenaIterMut 不能访问任何节点
+unsafe impl<T> Send for ArenaIterMut<'_, T> where T: Send {}
+unsafe impl<T> Sync for ArenaIterMut<'_, T> where T: Sync {}
+
+impl<'a, T> Iterator for ArenaIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 || self.next == NIL {
+            return None;
+        }
+        let i = self.next as usize;
+        assert!(i < self.slots, "ArenaLinked link {} out of bounds for {} slots", i, self.slots);
+        // SAFETY: i < slots，指针在 Vec 的有效范围内且 'a 期间 Vec 不会移动（约束 1）；
+        // 前 len 步的下标互不相同（约束 2），之前返回的引用都指向其他节点，这里的可变引用不会与它们重叠
+        let node = unsafe { &mut *self.nodes.add(i) };
+        self.next = node.next;
+        self.len -= 1;
+        node.element.as_mut()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for ArenaIterMut<'_, T> {}
+
+// 按值迭代 依次删除 head
+pub struct ArenaIntoIter<T>(ArenaLinked<T>);
+
+impl<T> Iterator for ArenaIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.unlink_after(NIL)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> ExactSizeIterator for ArenaIntoIter<T> {}
+
+impl<T> IntoIterator for ArenaLinked<T> {
+    type Item = T;
+    type IntoIter = ArenaIntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ArenaIntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ArenaLinked<T> {
+    type Item = &'a T;
+    type IntoIter = ArenaIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut ArenaLinked<T> {
+    type Item = &'a mut T;
+    type IntoIter = ArenaIterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+// 不可变链表节点 通过 Arc 被多个版本共享
+struct PersistentNode<T> {
+    element: T,
+    next: Option<Arc<PersistentNode<T>>>,
+}
+
+// 持久化（不可变）单链表 适合撤销历史、函数式风格的数据处理
+// 每次修改都返回新版本，新版本与旧版本共享未改变的节点，旧版本始终有效
+// cons/head/tail 为 O(1)，clone 只增加引用计数；节点用 Arc 共享，不同版本可以在线程间传递
+pub struct PersistentList<T> {
+    head: Option<Arc<PersistentNode<T>>>,
+    len: usize,
+}
+
+// 复制只共享节点 不要求 T: Clone
+impl<T> Clone for PersistentList<T> {
+    fn clone(&self) -> Self {
+        PersistentList {
+            head: self.head.clone(),
+            len: self.len,
+        }
+    }
+}
+
+// 只释放没有被其他版本共享的节点 循环实现避免长链表栈溢出
+// 多个线程同时释放共享同一段尾部的版本时，try_unwrap 可能都失败，最后一个引用会走默认的递归释放；
+// into_inner 保证恰好有一个线程拿到节点，由它继续循环释放
+impl<T> Drop for PersistentList<T> {
+    fn drop(&mut self) {
+        let mut node = self.head.take();
+        while let Some(n) = node {
+            match Arc::into_inner(n) {
+                Some(mut n) => node = n.next.take(),
+                None => break,
+            }
+        }
+    }
+}
+
+impl<T> Default for PersistentList<T> {
+    fn default() -> Self {
+        PersistentList::new()
+    }
+}
+
+impl<T> Debug for PersistentList<T> where T: Debug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> PartialEq for PersistentList<T> where T: PartialEq {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T> FromIterator<T> for PersistentList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        // 从后往前依次插入 head
+        let v: Vec<T> = iter.into_iter().collect();
+        v.into_iter().rev().fold(PersistentList::new(), |l, x| l.cons(x))
+    }
+}
+
+// Linked => PersistentList 转换 元素直接移动
+impl<T> From<Linked<T>> for PersistentList<T> {
+    fn from(l: Linked<T>) -> Self {
+        l.into_iter().collect()
+    }
+}
+
+// PersistentList => Linked 转换 节点可能被其他版本共享 元素需要复制
+impl<T> From<&PersistentList<T>> for Linked<T> where T: Clone {
+    fn from(l: &PersistentList<T>) -> Self {
+        l.iter().cloned().collect()
+    }
+}
+
+impl<T> From<PersistentList<T>> for Linked<T> where T: Clone {
+    fn from(l: PersistentList<T>) -> Self {
+        Linked::from(&l)
+    }
+}
+
+impl<T> PersistentList<T> {
+    pub fn new() -> Self {
+        PersistentList {
+            head: None,
+            len: 0,
+        }
+    }
+    // 获取长度
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    // 在 head 前插入 返回新版本，原链表整体作为新版本的后半部分
+    pub fn cons(&self, n: T) -> Self {
+        PersistentList {
+            head: Some(Arc::new(PersistentNode {
+                element: n,
+                next: self.head.clone(),
+            })),
+            len: self.len + 1,
+        }
+    }
+    // 第一个元素
+    pub fn head(&self) -> Option<&T> {
+        self.head.as_ref().map(|n| &n.element)
+    }
+    // 去掉第一个元素后的版本 空链表返回 None
+    pub fn tail(&self) -> Option<Self> {
+        self.head.as_ref().map(|n| PersistentList {
+            head: n.next.clone(),
+            len: self.len - 1,
+        })
+    }
+    pub fn iter(&self) -> PersistentIter<'_, T> {
+        PersistentIter {
+            next: self.head.as_deref(),
+            len: self.len,
+        }
+    }
+    // 两个版本是否共享同一组节点
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.head, &other.head) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+    // 反转 顺序全部改变无法共享 复制所有元素 O(n)
+    pub fn reverse(&self) -> Self where T: Clone {
+        self.iter().fold(PersistentList::new(), |l, x| l.cons(x.clone()))
+    }
+    // 拼接 复制当前链表的元素，other 整体共享 O(len)
+    pub fn append(&self, other: &Self) -> Self where T: Clone {
+        let v: Vec<&T> = self.iter().collect();
+        v.into_iter().rev().fold(other.clone(), |l, x| l.cons(x.clone()))
+    }
+}
+
+// 不可变链表迭代器
+pub struct PersistentIter<'a, T> {
+    next: Option<&'a PersistentNode<T>>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for PersistentIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| {
+            self.next = node.next.as_deref();
+            self.len -= 1;
+            &node.element
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for PersistentIter<'_, T> {}
+
+impl<'a, T> IntoIterator for &'a PersistentList<T> {
+    type Item = &'a T;
+    type IntoIter = PersistentIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
\ No newline at end of file
@@ -1,27 +1,65 @@
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
 use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use std::marker::PhantomData;
-use std::ops::Index;
+use std::mem;
 use crate::queue::QueueCache;
 
 // 基于动态数组实现LRU
 // 定义缓存 Trait
+// 下标越界、存储为空等情况返回 CacheError 而不是 panic
 pub trait Cache<T> {
     fn new() -> Self where Self: Sized;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     fn contains(&mut self, n: &T) -> bool;
-    fn remove(&mut self, i: usize) -> Option<T>;
-    fn insert(&mut self, i: usize, n: T);
-    fn pop(&mut self) -> Option<T>;
-    fn index(&mut self, i: usize) -> Option<&T>;
+    fn remove(&mut self, i: usize) -> Result<T, CacheError>;
+    fn insert(&mut self, i: usize, n: T) -> Result<(), CacheError>;
+    fn pop(&mut self) -> Result<T, CacheError>;
+    fn index(&mut self, i: usize) -> Result<&T, CacheError>;
+}
+
+// 存储结构操作错误
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheError {
+    // 下标越界
+    OutOfBounds { index: usize, len: usize },
+    // 存储为空
+    Empty,
+    // 存储已满 无法再插入
+    Full,
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::OutOfBounds { index, len } => write!(f, "index {} out of bounds for length {}", index, len),
+            CacheError::Empty => write!(f, "cache storage is empty"),
+            CacheError::Full => write!(f, "cache storage is full"),
+        }
+    }
 }
 
+impl Error for CacheError {}
+
 // 包装 Vec
 #[derive(Clone, Debug)]
-pub struct List<T> where T: Clone + PartialOrd + PartialEq {
+pub struct List<T> {
     inner: Vec<T>,
 }
 
 // 实现 Queue 特性
-impl<T> QueueCache<T> for List<T> where T: Clone + PartialOrd + PartialEq {
+impl<T> QueueCache<T> for List<T> {
     fn new() -> Self {
         List {
             inner: Vec::new()
@@ -33,16 +71,16 @@ impl<T> QueueCache<T> for List<T> where T: Clone + PartialOrd + PartialEq {
     }
 
     fn dequeue(&mut self) -> Option<T> {
-        if self.inner.len() == 0 {
+        if self.inner.is_empty() {
             None
         } else {
-            self.remove(0)
+            Some(self.inner.remove(0))
         }
     }
 }
 
 // Vec => List 转换
-impl<T> From<Vec<T>> for List<T> where T: Clone + PartialOrd + PartialEq {
+impl<T> From<Vec<T>> for List<T> {
     fn from(v: Vec<T>) -> Self {
         List {
             inner: v
@@ -51,84 +89,1388 @@ impl<T> From<Vec<T>> for List<T> where T: Clone + PartialOrd + PartialEq {
 }
 
 // List => Vec 转换
-impl<T> Into<Vec<T>> for List<T> where T: Clone + PartialOrd + PartialEq {
+impl<T> Into<Vec<T>> for List<T> {
     fn into(mut self) -> Vec<T> {
         self.inner
     }
 }
 
 // 实现 Cache Trait
-impl<T> Cache<T> for List<T> where T: Clone + PartialOrd + PartialEq {
+impl<T> Cache<T> for List<T> where T: PartialEq {
     fn new() -> Self {
         List {
             inner: Vec::new()
         }
     }
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
     fn contains(&mut self, n: &T) -> bool {
         self.inner.contains(&n)
     }
-    fn remove(&mut self, i: usize) -> Option<T> {
-        Some(self.inner.remove(i))
+    fn remove(&mut self, i: usize) -> Result<T, CacheError> {
+        if i >= self.inner.len() {
+            return Err(CacheError::OutOfBounds { index: i, len: self.inner.len() });
+        }
+        Ok(self.inner.remove(i))
+    }
+    fn insert(&mut self, i: usize, n: T) -> Result<(), CacheError> {
+        if i > self.inner.len() {
+            return Err(CacheError::OutOfBounds { index: i, len: self.inner.len() });
+        }
+        self.inner.insert(i, n);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<T, CacheError> {
+        self.inner.pop().ok_or(CacheError::Empty)
+    }
+
+    fn index(&mut self, i: usize) -> Result<&T, CacheError> {
+        let len = self.inner.len();
+        self.inner.get(i).ok_or(CacheError::OutOfBounds { index: i, len })
+    }
+}
+
+// 查询结果 命中/未命中/未命中并淘汰了 tail 元素
+// 按权重计算容量时一次插入可能淘汰多个元素
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome<T> {
+    Hit,
+    Miss,
+    MissEvicted(T),
+    MissEvictedMany(Vec<T>),
+}
+
+// 元素权重超过缓存总容量 拒绝插入并返回该元素
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightError<T> {
+    pub element: T,
+    pub weight: usize,
+    pub capacity: usize,
+}
+
+impl<T> fmt::Display for WeightError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "element weight {} exceeds cache capacity {}", self.weight, self.capacity)
+    }
+}
+
+impl<T> Error for WeightError<T> where T: Debug {}
+
+// 查询失败 元素权重超过容量或底层存储操作失败
+#[derive(Clone, Debug, PartialEq)]
+pub enum LookupError<T> {
+    Weight(WeightError<T>),
+    Cache(CacheError),
+}
+
+impl<T> fmt::Display for LookupError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupError::Weight(e) => write!(f, "{}", e),
+            LookupError::Cache(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<T> Error for LookupError<T> where T: Debug {}
+
+impl<T> From<CacheError> for LookupError<T> {
+    fn from(e: CacheError) -> Self {
+        LookupError::Cache(e)
+    }
+}
+
+// 权重计算函数
+#[derive(Clone)]
+pub struct Weigher<T>(Arc<dyn Fn(&T) -> usize + Send + Sync>);
+
+impl<T> Debug for Weigher<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Weigher")
+    }
+}
+
+// 命中统计
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    // 查询总次数
+    pub fn lookups(&self) -> u64 {
+        self.hits + self.misses
+    }
+    // 命中率 没有查询时为 0
+    pub fn hit_ratio(&self) -> f64 {
+        if self.lookups() == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.lookups() as f64
+    }
+    // 清空统计
+    pub fn reset(&mut self) {
+        *self = CacheStats::default();
+    }
+    // 根据查询结果累加
+    fn record<T>(&mut self, o: &Outcome<T>) {
+        match o {
+            Outcome::Hit => self.hits += 1,
+            Outcome::Miss => self.misses += 1,
+            Outcome::MissEvicted(_) => {
+                self.misses += 1;
+                self.evictions += 1;
+            }
+            Outcome::MissEvictedMany(v) => {
+                self.misses += 1;
+                self.evictions += v.len() as u64;
+            }
+        }
+    }
+}
+
+// 淘汰策略
+// Lru 负责查找元素所在下标和维护长度，元素在存储中的位置和淘汰哪个元素由策略决定
+// 命中时调用 hit，未命中时调用 miss（full 表示容量已满必须淘汰一个元素）
+// 按权重计算容量或缩小容量时需要连续淘汰多个元素，由 evict 每次淘汰一个，没有可淘汰的元素返回 None
+// 容量变化后调用 resize 调整策略内部与容量相关的参数
+pub trait EvictionPolicy<T> {
+    fn new(size: usize) -> Self where Self: Sized;
+    fn hit<C: Cache<T>>(&mut self, cache: &mut C, i: usize) -> Result<(), CacheError>;
+    fn miss<C: Cache<T>>(&mut self, cache: &mut C, x: T, full: bool) -> Result<Option<T>, CacheError>;
+    fn evict<C: Cache<T>>(&mut self, cache: &mut C) -> Result<Option<T>, CacheError>;
+    fn resize(&mut self, _size: usize) {}
+}
+
+// 把下标 from 的元素移动到下标 to
+fn relocate<C, T>(cache: &mut C, from: usize, to: usize) -> Result<(), CacheError> where C: Cache<T> {
+    let e = cache.remove(from)?;
+    cache.insert(to, e)
+}
+
+// 在幽灵队列中查找并删除元素
+fn forget<T>(ghost: &mut VecDeque<T>, x: &T) -> bool where T: PartialEq {
+    match ghost.iter().position(|e| e == x) {
+        Some(i) => {
+            ghost.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+// 最近最少使用：命中移动到 head，淘汰 tail
+#[derive(Clone, Debug, Default)]
+pub struct LruPolicy;
+
+impl<T> EvictionPolicy<T> for LruPolicy {
+    fn new(_: usize) -> Self {
+        LruPolicy
+    }
+    fn hit<C: Cache<T>>(&mut self, cache: &mut C, i: usize) -> Result<(), CacheError> {
+        relocate(cache, i, 0)
+    }
+    fn miss<C: Cache<T>>(&mut self, cache: &mut C, x: T, full: bool) -> Result<Option<T>, CacheError> {
+        let evicted = if full { self.evict(cache)? } else { None };
+        cache.insert(0, x)?;
+        Ok(evicted)
+    }
+    fn evict<C: Cache<T>>(&mut self, cache: &mut C) -> Result<Option<T>, CacheError> {
+        if cache.is_empty() {
+            return Ok(None);
+        }
+        cache.pop().map(Some)
+    }
+}
+
+// 最近最多使用：命中移动到 head，淘汰 head（适合循环扫描的访问模式）
+#[derive(Clone, Debug, Default)]
+pub struct MruPolicy;
+
+impl<T> EvictionPolicy<T> for MruPolicy {
+    fn new(_: usize) -> Self {
+        MruPolicy
+    }
+    fn hit<C: Cache<T>>(&mut self, cache: &mut C, i: usize) -> Result<(), CacheError> {
+        relocate(cache, i, 0)
+    }
+    fn miss<C: Cache<T>>(&mut self, cache: &mut C, x: T, full: bool) -> Result<Option<T>, CacheError> {
+        let evicted = if full { self.evict(cache)? } else { None };
+        cache.insert(0, x)?;
+        Ok(evicted)
+    }
+    fn evict<C: Cache<T>>(&mut self, cache: &mut C) -> Result<Option<T>, CacheError> {
+        if cache.is_empty() {
+            return Ok(None);
+        }
+        cache.remove(0).map(Some)
+    }
+}
+
+// 先进先出：命中不改变位置，淘汰最早进入的 tail
+#[derive(Clone, Debug, Default)]
+pub struct FifoPolicy;
+
+impl<T> EvictionPolicy<T> for FifoPolicy {
+    fn new(_: usize) -> Self {
+        FifoPolicy
+    }
+    fn hit<C: Cache<T>>(&mut self, _: &mut C, _: usize) -> Result<(), CacheError> {
+        Ok(())
+    }
+    fn miss<C: Cache<T>>(&mut self, cache: &mut C, x: T, full: bool) -> Result<Option<T>, CacheError> {
+        let evicted = if full { self.evict(cache)? } else { None };
+        cache.insert(0, x)?;
+        Ok(evicted)
+    }
+    fn evict<C: Cache<T>>(&mut self, cache: &mut C) -> Result<Option<T>, CacheError> {
+        if cache.is_empty() {
+            return Ok(None);
+        }
+        cache.pop().map(Some)
+    }
+}
+
+// 时钟（二次机会）：每个元素一个访问位，命中置位
+// 淘汰时检查 tail，访问位为 true 则清零并移动到 head 再给一次机会，为 false 则淘汰
+#[derive(Clone, Debug, Default)]
+pub struct ClockPolicy {
+    // 与存储下标一一对应的访问位
+    bits: Vec<bool>,
+}
+
+impl<T> EvictionPolicy<T> for ClockPolicy {
+    // 容量可能来自快照等外部数据 访问位随元素增长，不按容量预先分配
+    fn new(_: usize) -> Self {
+        ClockPolicy::default()
+    }
+    fn hit<C: Cache<T>>(&mut self, cache: &mut C, i: usize) -> Result<(), CacheError> {
+        match self.bits.get_mut(i) {
+            Some(b) => {
+                *b = true;
+                Ok(())
+            }
+            None => Err(CacheError::OutOfBounds { index: i, len: cache.len() }),
+        }
+    }
+    fn miss<C: Cache<T>>(&mut self, cache: &mut C, x: T, full: bool) -> Result<Option<T>, CacheError> {
+        let evicted = if full { self.evict(cache)? } else { None };
+        cache.insert(0, x)?;
+        self.bits.insert(0, false);
+        Ok(evicted)
     }
-    fn insert(&mut self, i: usize, n: T) {
-        self.inner.insert(i, n)
+    fn evict<C: Cache<T>>(&mut self, cache: &mut C) -> Result<Option<T>, CacheError> {
+        // 所有访问位都为 true 时最多转一圈就能找到淘汰对象
+        while let Some(referenced) = self.bits.pop() {
+            if referenced {
+                relocate(cache, self.bits.len(), 0)?;
+                self.bits.insert(0, false);
+            } else {
+                return cache.pop().map(Some);
+            }
+        }
+        Ok(None)
     }
+}
+
+// 2Q：新元素先进入 FIFO 队列 A1in，被淘汰后 key 记录在幽灵队列 A1out
+// 在 A1out 中再次被访问说明是热点数据，直接进入 LRU 队列 Am
+// 存储布局：[0, am) 为 Am（head 最新），[am, len) 为 A1in（am 处最新）
+#[derive(Clone, Debug)]
+pub struct TwoQueuePolicy<T> {
+    am: usize,
+    a1in: usize,
+    a1out: VecDeque<T>,
+    // A1in 和 A1out 的目标长度
+    kin: usize,
+    kout: usize,
+}
 
-    fn pop(&mut self) -> Option<T> {
-        self.inner.pop()
+impl<T> EvictionPolicy<T> for TwoQueuePolicy<T> where T: Clone + PartialEq {
+    fn new(size: usize) -> Self {
+        TwoQueuePolicy {
+            am: 0,
+            a1in: 0,
+            a1out: VecDeque::new(),
+            kin: (size / 4).max(1),
+            kout: (size / 2).max(1),
+        }
     }
+    fn hit<C: Cache<T>>(&mut self, cache: &mut C, i: usize) -> Result<(), CacheError> {
+        // A1in 中的命中不改变位置
+        if i < self.am {
+            relocate(cache, i, 0)?;
+        }
+        Ok(())
+    }
+    fn miss<C: Cache<T>>(&mut self, cache: &mut C, x: T, full: bool) -> Result<Option<T>, CacheError> {
+        // 先检查是否在 A1out 中 避免被本次淘汰挤出
+        let hot = forget(&mut self.a1out, &x);
+        let evicted = if full { self.evict(cache)? } else { None };
+        if hot {
+            cache.insert(0, x)?;
+            self.am += 1;
+        } else {
+            cache.insert(self.am, x)?;
+            self.a1in += 1;
+        }
+        Ok(evicted)
+    }
+    fn evict<C: Cache<T>>(&mut self, cache: &mut C) -> Result<Option<T>, CacheError> {
+        if self.a1in > self.kin || (self.am == 0 && self.a1in > 0) {
+            // 淘汰 A1in tail 并记录到 A1out
+            let e = cache.pop()?;
+            self.a1in -= 1;
+            self.a1out.push_front(e.clone());
+            self.a1out.truncate(self.kout);
+            Ok(Some(e))
+        } else if self.am > 0 {
+            // 淘汰 Am tail
+            let e = cache.remove(self.am - 1)?;
+            self.am -= 1;
+            Ok(Some(e))
+        } else {
+            Ok(None)
+        }
+    }
+    fn resize(&mut self, size: usize) {
+        self.kin = (size / 4).max(1);
+        self.kout = (size / 2).max(1);
+        self.a1out.truncate(self.kout);
+    }
+}
+
+// ARC 自适应替换：T1 保存只访问过一次的元素，T2 保存访问过多次的元素
+// B1、B2 分别记录从 T1、T2 淘汰的 key，命中幽灵队列时调整 T1 的目标长度 p
+// 存储布局：[0, t2) 为 T2（head 最新），[t2, len) 为 T1（t2 处最新）
+#[derive(Clone, Debug)]
+pub struct ArcPolicy<T> {
+    size: usize,
+    p: usize,
+    t1: usize,
+    t2: usize,
+    b1: VecDeque<T>,
+    b2: VecDeque<T>,
+}
 
-    fn index(&mut self, i: usize) -> Option<&T> {
-        Some(self.inner.index(i))
+impl<T> ArcPolicy<T> where T: Clone {
+    // 根据 p 决定从 T1 还是 T2 淘汰，被淘汰元素记录到对应的幽灵队列
+    fn replace<C: Cache<T>>(&mut self, cache: &mut C, in_b2: bool) -> Result<Option<T>, CacheError> {
+        if self.t1 > 0 && (self.t1 > self.p || (in_b2 && self.t1 == self.p) || self.t2 == 0) {
+            let e = cache.pop()?;
+            self.t1 -= 1;
+            self.b1.push_front(e.clone());
+            Ok(Some(e))
+        } else if self.t2 > 0 {
+            let e = cache.remove(self.t2 - 1)?;
+            self.t2 -= 1;
+            self.b2.push_front(e.clone());
+            Ok(Some(e))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T> EvictionPolicy<T> for ArcPolicy<T> where T: Clone + PartialEq {
+    fn new(size: usize) -> Self {
+        ArcPolicy {
+            size,
+            p: 0,
+            t1: 0,
+            t2: 0,
+            b1: VecDeque::new(),
+            b2: VecDeque::new(),
+        }
+    }
+    fn hit<C: Cache<T>>(&mut self, cache: &mut C, i: usize) -> Result<(), CacheError> {
+        relocate(cache, i, 0)?;
+        // T1 中的元素再次命中进入 T2
+        if i >= self.t2 {
+            self.t1 -= 1;
+            self.t2 += 1;
+        }
+        Ok(())
+    }
+    fn miss<C: Cache<T>>(&mut self, cache: &mut C, x: T, full: bool) -> Result<Option<T>, CacheError> {
+        let mut evicted = None;
+        if forget(&mut self.b1, &x) {
+            // 命中 B1 说明 T1 偏小 增大 p
+            let delta = (self.b2.len() / (self.b1.len() + 1)).max(1);
+            self.p = self.p.saturating_add(delta).min(self.size);
+            if full {
+                evicted = self.replace(cache, false)?;
+            }
+            cache.insert(0, x)?;
+            self.t2 += 1;
+            return Ok(evicted);
+        }
+        if forget(&mut self.b2, &x) {
+            // 命中 B2 说明 T2 偏小 减小 p
+            let delta = (self.b1.len() / (self.b2.len() + 1)).max(1);
+            self.p = self.p.saturating_sub(delta);
+            if full {
+                evicted = self.replace(cache, true)?;
+            }
+            cache.insert(0, x)?;
+            self.t2 += 1;
+            return Ok(evicted);
+        }
+        if self.t1 + self.b1.len() >= self.size {
+            if self.t1 < self.size {
+                self.b1.pop_back();
+                if full {
+                    evicted = self.replace(cache, false)?;
+                }
+            } else if self.t1 > 0 {
+                // B1 为空 直接淘汰 T1 tail 不记录（容量为 0 时 T1 为空 没有可淘汰的元素）
+                evicted = Some(cache.pop()?);
+                self.t1 -= 1;
+            }
+        } else {
+            let total = self.t1 + self.t2 + self.b1.len() + self.b2.len();
+            if total >= self.size {
+                if total >= self.size.saturating_mul(2) {
+                    self.b2.pop_back();
+                }
+                if full {
+                    evicted = self.replace(cache, false)?;
+                }
+            }
+        }
+        cache.insert(self.t2, x)?;
+        self.t1 += 1;
+        Ok(evicted)
+    }
+    fn evict<C: Cache<T>>(&mut self, cache: &mut C) -> Result<Option<T>, CacheError> {
+        self.replace(cache, false)
+    }
+    fn resize(&mut self, size: usize) {
+        // 保持 T1 + B1 <= c 且 T1 + T2 + B1 + B2 <= 2c
+        self.size = size;
+        self.p = self.p.min(size);
+        self.b1.truncate(size.saturating_sub(self.t1));
+        self.b2.truncate(size.saturating_mul(2).saturating_sub(self.t1 + self.t2 + self.b1.len()));
     }
 }
 
 // 定义容量和当前长度（没有采用动态获取）
+// P 为淘汰策略 默认 LRU
+// 设置 weigher 后容量按元素权重之和计算，否则每个元素权重为 1
 #[derive(Clone, Debug)]
-pub struct Lru<C, T> where C: Cache<T>, T: Clone + PartialOrd + PartialEq {
+pub struct Lru<C, T, P = LruPolicy> where C: Cache<T> {
     // cache 作为泛型 支持不同得数据结构
     cache: C,
     size: usize,
     len: usize,
+    // 当前权重之和
+    weight: usize,
+    weigher: Option<Weigher<T>>,
+    stats: CacheStats,
+    policy: P,
     mark: PhantomData<T>,
 }
 
-impl<C, T> Lru<C, T> where C: Cache<T> + Debug, T: Clone + Debug + PartialOrd + PartialEq {
+impl<C, T, P> Lru<C, T, P> where C: Cache<T>, T: PartialEq, P: EvictionPolicy<T> {
     // 根据容量初始化
     pub fn new(n: usize) -> Self {
         Lru {
             cache: Cache::new(),
             size: n,
             len: 0,
+            weight: 0,
+            weigher: None,
+            stats: CacheStats::default(),
+            policy: P::new(n),
             mark: Default::default(),
         }
     }
+    // 根据总权重和权重计算函数初始化
+    pub fn with_weigher<F>(n: usize, f: F) -> Self where F: Fn(&T) -> usize + Send + Sync + 'static {
+        let mut lru = Lru::new(n);
+        lru.weigher = Some(Weigher(Arc::new(f)));
+        lru
+    }
     pub fn cache(&self) -> &C {
         &self.cache
     }
-    // 策略
-    pub fn lookup(&mut self, x: T) {
-        // 元素存在则将元素移动到 head
-        if self.cache.contains(&x) {
-            let mut index = 0;
-            for i in 0..self.len {
-                if let Some(c) = self.cache.index(i) {
-                    if c.clone() == x.clone() {
-                        index = i;
-                    }
+    // 元素个数
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    // 当前权重之和
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+    // 容量
+    pub fn capacity(&self) -> usize {
+        self.size
+    }
+    // 命中统计
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+    // 清空命中统计
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+    // 计算元素权重
+    fn weigh(&self, x: &T) -> usize {
+        match self.weigher {
+            Some(Weigher(ref f)) => f(x),
+            None => 1,
+        }
+    }
+    // 查找元素所在下标
+    fn position(&mut self, x: &T) -> Option<usize> {
+        if !self.cache.contains(x) {
+            return None;
+        }
+        (0..self.len).find(|&i| matches!(self.cache.index(i), Ok(e) if e == x))
+    }
+    // 策略 返回命中/未命中/被淘汰的元素，权重超过容量的元素不会被缓存
+    // 存储操作失败时直接 panic：可能是策略记录的布局和存储不一致，也可能是存储本身会失败（例如 ArenaLinked 已满返回 CacheError::Full）
+    // 存储可能失败时只能使用 try_lookup
+    pub fn lookup(&mut self, x: T) -> Outcome<T> {
+        match self.try_lookup(x) {
+            Ok(o) => o,
+            Err(LookupError::Weight(_)) => Outcome::Miss,
+            Err(LookupError::Cache(e)) => panic!("cache storage failed during lookup: {}", e),
+        }
+    }
+    // 同 lookup，权重超过容量或存储操作失败时返回错误
+    pub fn try_lookup(&mut self, x: T) -> Result<Outcome<T>, LookupError<T>> {
+        // 元素存在 由策略调整位置
+        if let Some(i) = self.position(&x) {
+            self.policy.hit(&mut self.cache, i)?;
+            self.stats.record(&Outcome::<T>::Hit);
+            return Ok(Outcome::Hit);
+        }
+        // 容量为 0 时无法缓存 不插入也不淘汰
+        if self.weigher.is_none() && self.size == 0 {
+            self.stats.record(&Outcome::<T>::Miss);
+            return Ok(Outcome::Miss);
+        }
+        let w = self.weigh(&x);
+        if w > self.size {
+            // 拒绝插入 不清空缓存
+            self.stats.record(&Outcome::<T>::Miss);
+            return Err(LookupError::Weight(WeightError {
+                element: x,
+                weight: w,
+                capacity: self.size,
+            }));
+        }
+        // 元素不存在 容量不足时由策略选择淘汰元素
+        let mut evicted = Vec::new();
+        let full = if self.weigher.is_some() {
+            // 按权重淘汰 直到放得下新元素，权重之和溢出同样视为放不下
+            while !matches!(self.weight.checked_add(w), Some(t) if t <= self.size) {
+                match self.policy.evict(&mut self.cache)? {
+                    Some(e) => self.evicted(e, &mut evicted),
+                    None => break,
                 }
             }
-            self.cache.remove(index);
-            self.cache.insert(0, x);
+            false
         } else {
-            // 容量够 直接添加
-            if self.len < self.size {
-                self.len += 1;
-            } else {
-                // 容量不足 先删除 tail 元素
-                self.cache.pop();
+            self.len >= self.size
+        };
+        if let Some(e) = self.policy.miss(&mut self.cache, x, full)? {
+            self.evicted(e, &mut evicted);
+        }
+        self.len += 1;
+        self.weight = self.weight.saturating_add(w);
+        let outcome = match evicted.len() {
+            0 => Outcome::Miss,
+            1 => Outcome::MissEvicted(evicted.remove(0)),
+            _ => Outcome::MissEvictedMany(evicted),
+        };
+        self.stats.record(&outcome);
+        Ok(outcome)
+    }
+    // 调整容量 缩小时由策略依次淘汰直到不超过新容量，按淘汰顺序返回被淘汰的元素
+    pub fn resize(&mut self, n: usize) -> Result<Vec<T>, CacheError> {
+        self.size = n;
+        let mut evicted = Vec::new();
+        loop {
+            let used = if self.weigher.is_some() { self.weight } else { self.len };
+            if used <= n {
+                break;
+            }
+            match self.policy.evict(&mut self.cache)? {
+                Some(e) => self.evicted(e, &mut evicted),
+                None => break,
+            }
+        }
+        self.stats.evictions += evicted.len() as u64;
+        self.policy.resize(n);
+        Ok(evicted)
+    }
+    // 记录被淘汰的元素 更新长度和权重
+    fn evicted(&mut self, e: T, v: &mut Vec<T>) {
+        self.len -= 1;
+        self.weight = self.weight.saturating_sub(self.weigh(&e));
+        v.push(e);
+    }
+}
+
+// 快照 把缓存内容按访问顺序写入 io::Write，重启后从 io::Read 恢复，避免缓存冷启动
+// 格式（整数均为小端）：
+// 魔数 b"LRUS" | 版本 u8 | 是否按权重计算容量 u8 | 容量 u64 | 元素个数 u64 | 元素（head => tail）
+// 版本 1 没有记录权重 无法区分带权重的缓存，不再支持
+const SNAPSHOT_MAGIC: &[u8; 4] = b"LRUS";
+const SNAPSHOT_VERSION: u8 = 2;
+
+// 快照错误
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    // 不是快照数据
+    BadMagic,
+    UnsupportedVersion(u8),
+    // 数据不完整
+    Truncated,
+    // 数据内容错误
+    Corrupt(String),
+    // 带权重的快照需要用 restore_with_weigher 恢复，不带权重的只能用 restore 恢复
+    WeigherMismatch { weighted: bool },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "snapshot io error: {}", e),
+            SnapshotError::BadMagic => write!(f, "not an lru snapshot"),
+            SnapshotError::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {}", v),
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::Corrupt(s) => write!(f, "snapshot is corrupt: {}", s),
+            SnapshotError::WeigherMismatch { weighted: true } => write!(f, "snapshot of a weighted cache requires a weigher"),
+            SnapshotError::WeigherMismatch { weighted: false } => write!(f, "snapshot of an unweighted cache cannot use a weigher"),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => SnapshotError::Truncated,
+            _ => SnapshotError::Io(e),
+        }
+    }
+}
+
+// 元素的二进制编码
+pub trait Codec: Sized {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()>;
+    fn decode<R: Read>(r: &mut R) -> Result<Self, SnapshotError>;
+}
+
+// 整数按固定长度小端编码
+macro_rules! int_codec {
+    ($($t:ty),*) => {
+        $(
+            impl Codec for $t {
+                fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+                    w.write_all(&self.to_le_bytes())
+                }
+                fn decode<R: Read>(r: &mut R) -> Result<Self, SnapshotError> {
+                    let mut buf = [0u8; mem::size_of::<$t>()];
+                    r.read_exact(&mut buf)?;
+                    Ok(<$t>::from_le_bytes(buf))
+                }
             }
-            self.cache.insert(0, x);
+        )*
+    };
+}
+
+int_codec!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+// usize/isize 统一按 64 位编码 不同平台之间可以互相恢复
+impl Codec for usize {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as u64).encode(w)
+    }
+    fn decode<R: Read>(r: &mut R) -> Result<Self, SnapshotError> {
+        let n = u64::decode(r)?;
+        usize::try_from(n).map_err(|_| SnapshotError::Corrupt(format!("{} does not fit in usize", n)))
+    }
+}
+
+impl Codec for isize {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as i64).encode(w)
+    }
+    fn decode<R: Read>(r: &mut R) -> Result<Self, SnapshotError> {
+        let n = i64::decode(r)?;
+        isize::try_from(n).map_err(|_| SnapshotError::Corrupt(format!("{} does not fit in isize", n)))
+    }
+}
+
+impl Codec for bool {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as u8).encode(w)
+    }
+    fn decode<R: Read>(r: &mut R) -> Result<Self, SnapshotError> {
+        match u8::decode(r)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(SnapshotError::Corrupt(format!("invalid bool {}", b))),
+        }
+    }
+}
+
+// 字节串 长度 u64 + 内容
+impl Codec for Vec<u8> {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.len().encode(w)?;
+        w.write_all(self)
+    }
+    fn decode<R: Read>(r: &mut R) -> Result<Self, SnapshotError> {
+        let len = u64::decode(r)?;
+        // 按实际读到的长度分配 错误的长度不会导致申请超大内存
+        let mut buf = Vec::new();
+        r.take(len).read_to_end(&mut buf)?;
+        if buf.len() as u64 != len {
+            return Err(SnapshotError::Truncated);
+        }
+        Ok(buf)
+    }
+}
+
+impl Codec for String {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.len().encode(w)?;
+        w.write_all(self.as_bytes())
+    }
+    fn decode<R: Read>(r: &mut R) -> Result<Self, SnapshotError> {
+        String::from_utf8(Vec::decode(r)?).map_err(|e| SnapshotError::Corrupt(e.to_string()))
+    }
+}
+
+impl<A, B> Codec for (A, B) where A: Codec, B: Codec {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.0.encode(w)?;
+        self.1.encode(w)
+    }
+    fn decode<R: Read>(r: &mut R) -> Result<Self, SnapshotError> {
+        Ok((A::decode(r)?, B::decode(r)?))
+    }
+}
+
+impl<C, T, P> Lru<C, T, P> where C: Cache<T>, T: PartialEq + Codec, P: EvictionPolicy<T> {
+    // 写入快照 元素按存储顺序（LRU 策略下即访问顺序 head => tail）
+    pub fn snapshot<W: Write>(&mut self, mut w: W) -> Result<(), SnapshotError> {
+        w.write_all(SNAPSHOT_MAGIC)?;
+        SNAPSHOT_VERSION.encode(&mut w)?;
+        self.weigher.is_some().encode(&mut w)?;
+        self.size.encode(&mut w)?;
+        self.len.encode(&mut w)?;
+        for i in 0..self.len {
+            match self.cache.index(i) {
+                Ok(x) => x.encode(&mut w)?,
+                Err(e) => return Err(SnapshotError::Corrupt(e.to_string())),
+            }
+        }
+        w.flush()?;
+        Ok(())
+    }
+    // 从快照恢复 从 tail 到 head 依次查询重建相同的顺序
+    // 其他策略的内部状态（访问位、幽灵队列等）不保存，恢复后重新积累
+    // 容量来自外部数据，只用于限制元素个数，不会按容量预先分配
+    pub fn restore<R: Read>(r: R) -> Result<Self, SnapshotError> {
+        Lru::restore_from(r, None)
+    }
+    // 从带权重的快照恢复 需要提供和写入时相同的权重计算函数
+    pub fn restore_with_weigher<R, F>(r: R, f: F) -> Result<Self, SnapshotError>
+    where R: Read, F: Fn(&T) -> usize + Send + Sync + 'static {
+        Lru::restore_from(r, Some(Weigher(Arc::new(f))))
+    }
+    fn restore_from<R: Read>(mut r: R, weigher: Option<Weigher<T>>) -> Result<Self, SnapshotError> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = u8::decode(&mut r)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let weighted = bool::decode(&mut r)?;
+        if weighted != weigher.is_some() {
+            return Err(SnapshotError::WeigherMismatch { weighted });
+        }
+        let size = usize::decode(&mut r)?;
+        let len = usize::decode(&mut r)?;
+        // 按权重计算时权重为 0 的元素不占容量 个数可以超过容量
+        if !weighted && len > size {
+            return Err(SnapshotError::Corrupt(format!("{} elements exceed capacity {}", len, size)));
+        }
+        // 按实际读到的元素增长 错误的个数不会导致申请超大内存
+        let mut v = Vec::new();
+        for _ in 0..len {
+            v.push(T::decode(&mut r)?);
+        }
+        let mut lru = Lru::new(size);
+        lru.weigher = weigher;
+        for x in v.into_iter().rev() {
+            match lru.try_lookup(x) {
+                Ok(Outcome::Miss) => {}
+                Ok(Outcome::Hit) => return Err(SnapshotError::Corrupt("duplicate element".to_string())),
+                Ok(_) => return Err(SnapshotError::Corrupt(format!("elements exceed capacity {}", size))),
+                Err(e) => return Err(SnapshotError::Corrupt(e.to_string())),
+            }
+        }
+        lru.reset_stats();
+        Ok(lru)
+    }
+}
+
+// 时钟 返回从某个固定起点开始经过的时间，测试中可以替换为手动时钟
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+// 系统时钟 以创建时刻为起点
+#[derive(Clone, Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+// 手动时钟 clone 之后共享同一个时间，测试中推进时间不需要 sleep
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock::default()
+    }
+    // 推进时间 超过 u64 纳秒时停在最大值
+    pub fn advance(&self, d: Duration) {
+        let d = nanos(d);
+        let _ = self.nanos.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| Some(n.saturating_add(d)));
+    }
+    // 设置时间 超过 u64 纳秒时取最大值
+    pub fn set(&self, d: Duration) {
+        self.nanos.store(nanos(d), Ordering::SeqCst);
+    }
+}
+
+// Duration 转为纳秒 超出 u64 时饱和
+fn nanos(d: Duration) -> u64 {
+    u64::try_from(d.as_nanos()).unwrap_or(u64::MAX)
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+}
+
+// 基于散列表 + 双向链表实现 O(1) 的 LRU
+// 散列表保存 key => 节点下标，双向链表按访问时间串联节点（head 最新 tail 最旧）
+// 节点统一存放在 Vec 中通过下标互相指向，删除后的空位记录在 free 中复用
+// 查询：散列表定位节点 O(1)，把节点摘下挂到 head O(1)
+// 插入：容量不足时摘掉 tail 节点并从散列表删除 O(1)
+// 过期：每个节点可以设置过期时间，访问时发现过期则删除（惰性删除），也可以调用 purge_expired 主动清理
+// 监听：节点被移除时通知所有监听器，并附带移除原因（例如淘汰时把脏数据写回）
+#[derive(Clone, Debug)]
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    // 过期时刻 None 表示永不过期
+    expires: Option<Duration>,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+// 节点被移除的原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemovalCause {
+    // 容量不足被淘汰
+    Capacity,
+    // 调用 remove/pop_lru 主动删除未过期的节点
+    Explicit,
+    // 过期 包括 remove/pop_lru 遇到的已过期节点
+    Expired,
+    // 同一个 key 插入了新的 value
+    Replaced,
+}
+
+// 移除监听器
+type Listener<K, V> = Arc<dyn Fn(&K, &V, RemovalCause) + Send + Sync>;
+
+#[derive(Clone)]
+struct Listeners<K, V>(Vec<Listener<K, V>>);
+
+impl<K, V> Debug for Listeners<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Listeners({})", self.0.len())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LruMap<K, V, C = SystemClock> where K: Hash + Eq + Clone, C: Clock {
+    map: HashMap<K, usize>,
+    // 节点存储，被删除的位置为 None
+    entries: Vec<Option<Entry<K, V>>>,
+    // 空闲下标
+    free: Vec<usize>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    size: usize,
+    // 默认存活时间
+    ttl: Option<Duration>,
+    clock: C,
+    listeners: Listeners<K, V>,
+}
+
+impl<K, V> LruMap<K, V> where K: Hash + Eq + Clone {
+    // 根据容量初始化
+    pub fn new(n: usize) -> Self {
+        LruMap::with_clock(n, SystemClock::default())
+    }
+    // 根据容量和默认存活时间初始化
+    pub fn with_ttl(n: usize, ttl: Duration) -> Self {
+        let mut m = LruMap::new(n);
+        m.ttl = Some(ttl);
+        m
+    }
+}
+
+impl<K, V, C> LruMap<K, V, C> where K: Hash + Eq + Clone, C: Clock {
+    // 根据容量和时钟初始化
+    // 容量只是上限（usize::MAX 表示不限制），不按容量预先分配，存储随插入增长
+    pub fn with_clock(n: usize, clock: C) -> Self {
+        LruMap {
+            map: HashMap::new(),
+            entries: Vec::new(),
+            free: Vec::new(),
+            head: None,
+            tail: None,
+            size: n,
+            ttl: None,
+            clock,
+            listeners: Listeners(Vec::new()),
+        }
+    }
+    // 注册移除监听器
+    pub fn add_listener<F>(&mut self, f: F) where F: Fn(&K, &V, RemovalCause) + Send + Sync + 'static {
+        self.listeners.0.push(Arc::new(f));
+    }
+    // 设置默认存活时间 只影响之后插入的节点
+    pub fn set_default_ttl(&mut self, ttl: Option<Duration>) {
+        self.ttl = ttl;
+    }
+    // 获取长度（包含已过期但还未清理的节点）
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    // 获取容量
+    pub fn capacity(&self) -> usize {
+        self.size
+    }
+    pub fn contains(&self, k: &K) -> bool {
+        match self.map.get(k) {
+            Some(&i) => !self.expired(i),
+            None => false,
+        }
+    }
+    // 查询并将节点移动到 head
+    pub fn get(&mut self, k: &K) -> Option<&V> {
+        let i = self.live(k)?;
+        self.detach(i);
+        self.attach(i);
+        self.entries[i].as_ref().map(|e| &e.value)
+    }
+    // 查询可变引用并将节点移动到 head
+    pub fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        let i = self.live(k)?;
+        self.detach(i);
+        self.attach(i);
+        self.entries[i].as_mut().map(|e| &mut e.value)
+    }
+    // 查询但不改变访问顺序
+    pub fn peek(&self, k: &K) -> Option<&V> {
+        let i = *self.map.get(k)?;
+        if self.expired(i) {
+            return None;
+        }
+        self.entries[i].as_ref().map(|e| &e.value)
+    }
+    // 插入 key 已存在则替换 value，容量不足时淘汰 tail 并返回被淘汰的键值对
+    pub fn put(&mut self, k: K, v: V) -> Option<(K, V)> {
+        self.insert(k, v, self.ttl)
+    }
+    // 指定存活时间插入
+    pub fn put_with_ttl(&mut self, k: K, v: V, ttl: Duration) -> Option<(K, V)> {
+        self.insert(k, v, Some(ttl))
+    }
+    // 查询 不存在（或已过期）时调用 loader 加载并插入，返回 value 的拷贝
+    pub fn get_or_insert_with<F>(&mut self, k: K, f: F) -> V where V: Clone, F: FnOnce() -> V {
+        match self.get_or_try_insert_with(k, || Ok::<V, Infallible>(f())) {
+            Ok(v) => v,
+            Err(e) => match e {},
+        }
+    }
+    // 同 get_or_insert_with，loader 失败时不插入并返回错误
+    pub fn get_or_try_insert_with<F, E>(&mut self, k: K, f: F) -> Result<V, E> where V: Clone, F: FnOnce() -> Result<V, E> {
+        if let Some(v) = self.get(&k) {
+            return Ok(v.clone());
+        }
+        let v = f()?;
+        self.insert(k, v.clone(), self.ttl);
+        Ok(v)
+    }
+    // 剩余存活时间 永不过期返回 None
+    pub fn ttl(&self, k: &K) -> Option<Duration> {
+        let i = *self.map.get(k)?;
+        let expires = self.entries[i].as_ref()?.expires?;
+        expires.checked_sub(self.clock.now())
+    }
+    // 清理所有过期节点 返回被清理的键值对（从旧到新）
+    pub fn purge_expired(&mut self) -> Vec<(K, V)> {
+        let mut v = Vec::new();
+        let mut node = self.tail;
+        while let Some(i) = node {
+            node = self.entries[i].as_ref().and_then(|e| e.prev);
+            if self.expired(i) {
+                if let Some(e) = self.take(i, RemovalCause::Expired) {
+                    v.push(e);
+                }
+            }
+        }
+        v
+    }
+    // 节点是否过期
+    fn expired(&self, i: usize) -> bool {
+        match self.entries[i].as_ref().and_then(|e| e.expires) {
+            Some(expires) => self.clock.now() >= expires,
+            None => false,
+        }
+    }
+    // 查找未过期的节点 过期则删除
+    fn live(&mut self, k: &K) -> Option<usize> {
+        let i = *self.map.get(k)?;
+        if self.expired(i) {
+            self.take(i, RemovalCause::Expired);
+            return None;
+        }
+        Some(i)
+    }
+    fn insert(&mut self, k: K, v: V, ttl: Option<Duration>) -> Option<(K, V)> {
+        // 过期时间溢出时视为永不过期
+        let expires = ttl.and_then(|t| self.clock.now().checked_add(t));
+        // 已过期的节点按过期删除 再作为新节点插入
+        if let Some(i) = self.live(&k) {
+            if let Some(e) = self.entries[i].as_mut() {
+                let old = mem::replace(&mut e.value, v);
+                e.expires = expires;
+                self.notify(&k, &old, RemovalCause::Replaced);
+            }
+            self.detach(i);
+            self.attach(i);
+            return None;
+        }
+        // 容量为 0 时无法缓存 直接返回
+        if self.size == 0 {
+            self.notify(&k, &v, RemovalCause::Capacity);
+            return Some((k, v));
+        }
+        let mut evicted = None;
+        if self.map.len() >= self.size {
+            evicted = self.tail.and_then(|i| self.take(i, RemovalCause::Capacity));
+        }
+        let entry = Entry {
+            key: k.clone(),
+            value: v,
+            expires,
+            prev: None,
+            next: None,
+        };
+        // 优先复用空闲位置
+        let i = match self.free.pop() {
+            Some(i) => {
+                self.entries[i] = Some(entry);
+                i
+            }
+            None => {
+                self.entries.push(Some(entry));
+                self.entries.len() - 1
+            }
+        };
+        self.attach(i);
+        self.map.insert(k, i);
+        evicted
+    }
+    // 删除最久未使用的未过期节点 途经的过期节点按过期删除
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        while let Some(i) = self.tail {
+            if !self.expired(i) {
+                return self.take(i, RemovalCause::Explicit);
+            }
+            self.take(i, RemovalCause::Expired);
+        }
+        None
+    }
+    // 根据 key 删除节点 已过期的按过期删除并返回 None
+    pub fn remove(&mut self, k: &K) -> Option<V> {
+        let i = self.live(k)?;
+        self.take(i, RemovalCause::Explicit).map(|(_, v)| v)
+    }
+    // 摘下节点并释放位置 通知监听器
+    fn take(&mut self, i: usize, cause: RemovalCause) -> Option<(K, V)> {
+        self.detach(i);
+        let e = self.entries[i].take()?;
+        self.map.remove(&e.key);
+        self.free.push(i);
+        self.notify(&e.key, &e.value, cause);
+        Some((e.key, e.value))
+    }
+    fn notify(&self, k: &K, v: &V, cause: RemovalCause) {
+        for f in self.listeners.0.iter() {
+            f(k, v, cause);
+        }
+    }
+    // 把节点从链表中摘下 prev <=> next 直接相连
+    fn detach(&mut self, i: usize) {
+        let (prev, next) = match self.entries[i].as_mut() {
+            Some(e) => (e.prev.take(), e.next.take()),
+            None => return,
+        };
+        match prev {
+            Some(p) => {
+                if let Some(e) = self.entries[p].as_mut() {
+                    e.next = next;
+                }
+            }
+            None => self.head = next,
+        }
+        match next {
+            Some(n) => {
+                if let Some(e) = self.entries[n].as_mut() {
+                    e.prev = prev;
+                }
+            }
+            None => self.tail = prev,
+        }
+    }
+    // 把节点挂到 head
+    fn attach(&mut self, i: usize) {
+        let head = self.head;
+        if let Some(e) = self.entries[i].as_mut() {
+            e.prev = None;
+            e.next = head;
+        }
+        if let Some(h) = head {
+            if let Some(e) = self.entries[h].as_mut() {
+                e.prev = Some(i);
+            }
+        }
+        self.head = Some(i);
+        if self.tail.is_none() {
+            self.tail = Some(i);
+        }
+    }
+    // 按访问顺序（最新 => 最旧）返回所有 key
+    pub fn keys(&self) -> Vec<K> {
+        let mut v = Vec::with_capacity(self.len());
+        let mut node = self.head;
+        while let Some(i) = node {
+            match self.entries[i].as_ref() {
+                Some(e) => {
+                    v.push(e.key.clone());
+                    node = e.next;
+                }
+                None => break,
+            }
+        }
+        v
+    }
+}
+
+// 分片 LRU 多线程共享
+// 按 key 的散列值把数据分到 N 个分片，每个分片是一个独立加锁的 LruMap
+// 不同分片的访问互不阻塞，相比一把全局锁大幅减少线程竞争
+// 方法都只需要 &self，可以放进 Arc 在线程间共享
+#[derive(Debug)]
+struct Shard<K, V> where K: Hash + Eq + Clone {
+    map: LruMap<K, V>,
+    stats: CacheStats,
+}
+
+#[derive(Debug)]
+pub struct ShardedLru<K, V> where K: Hash + Eq + Clone {
+    shards: Vec<Mutex<Shard<K, V>>>,
+    hasher: RandomState,
+}
+
+impl<K, V> ShardedLru<K, V> where K: Hash + Eq + Clone {
+    // 根据总容量和分片数初始化，容量平均分配到每个分片，余数分给前 n % shards 个分片，总和恰好为 n
+    // 分片数不超过容量，避免出现容量为 0 的分片（落到这些分片的键永远无法缓存）
+    pub fn new(n: usize, shards: usize) -> Self {
+        let shards = shards.min(n).max(1);
+        let (per, rest) = (n / shards, n % shards);
+        ShardedLru {
+            shards: (0..shards)
+                .map(|i| Mutex::new(Shard {
+                    map: LruMap::new(per + usize::from(i < rest)),
+                    stats: CacheStats::default(),
+                }))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+    // 分片数
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+    // 总容量
+    pub fn capacity(&self) -> usize {
+        self.shards.iter().map(|s| self.lock(s).map.capacity()).sum()
+    }
+    // 元素个数（逐个分片加锁统计 并发写入时只是近似值）
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| self.lock(s).map.len()).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn contains(&self, k: &K) -> bool {
+        self.shard(k).map.contains(k)
+    }
+    // 查询 返回 value 的拷贝
+    pub fn get(&self, k: &K) -> Option<V> where V: Clone {
+        let mut s = self.shard(k);
+        let v = s.map.get(k).cloned();
+        match v {
+            Some(_) => s.stats.hits += 1,
+            None => s.stats.misses += 1,
+        }
+        v
+    }
+    // 插入 返回被淘汰的键值对
+    pub fn put(&self, k: K, v: V) -> Option<(K, V)> {
+        let mut s = self.shard(&k);
+        let evicted = s.map.put(k, v);
+        if evicted.is_some() {
+            s.stats.evictions += 1;
+        }
+        evicted
+    }
+    pub fn remove(&self, k: &K) -> Option<V> {
+        self.shard(k).map.remove(k)
+    }
+    // 汇总所有分片的命中统计
+    pub fn stats(&self) -> CacheStats {
+        self.shards.iter().fold(CacheStats::default(), |mut total, s| {
+            let s = self.lock(s);
+            total.hits += s.stats.hits;
+            total.misses += s.stats.misses;
+            total.evictions += s.stats.evictions;
+            total
+        })
+    }
+    // 清空所有分片的命中统计
+    pub fn reset_stats(&self) {
+        for s in self.shards.iter() {
+            self.lock(s).stats.reset();
+        }
+    }
+    // 根据 key 定位分片并加锁
+    fn shard(&self, k: &K) -> MutexGuard<'_, Shard<K, V>> {
+        let i = (self.hasher.hash_one(k) % self.shards.len() as u64) as usize;
+        self.lock(&self.shards[i])
+    }
+    // 持有锁的线程 panic 后锁会被标记为中毒，这里忽略中毒标记直接取回数据继续使用
+    fn lock<'a>(&self, s: &'a Mutex<Shard<K, V>>) -> MutexGuard<'a, Shard<K, V>> {
+        s.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Belady 最优替换（MIN）离线算法 作为各种淘汰策略命中率的上限
+// 预先知道整个访问序列，淘汰下一次访问最远（或不再访问）的元素
+// 新元素的下一次访问比缓存中所有元素都远时不缓存它（旁路）
+// 元素权重不同时按同样规则淘汰直到放得下，此时结果是近似最优
+// 时间复杂度 O(nlogn)：倒序计算每个位置的下一次访问位置，缓存按下一次访问位置放入有序集合
+pub fn belady_min<T, F>(trace: &[T], n: usize, weigh: F) -> CacheStats where T: Hash + Eq, F: Fn(&T) -> usize {
+    // next[i] 为 trace[i] 下一次出现的位置 不再出现为 usize::MAX
+    let mut next = vec![usize::MAX; trace.len()];
+    let mut seen: HashMap<&T, usize> = HashMap::new();
+    for (i, x) in trace.iter().enumerate().rev() {
+        if let Some(j) = seen.insert(x, i) {
+            next[i] = j;
+        }
+    }
+    let mut stats = CacheStats::default();
+    // key => (下一次访问位置, 当前位置)，当前位置保证集合中的元素唯一
+    let mut cached: HashMap<&T, (usize, usize)> = HashMap::new();
+    let mut order: BTreeSet<(usize, usize)> = BTreeSet::new();
+    let mut weight = 0;
+    for (i, x) in trace.iter().enumerate() {
+        if let Some(old) = cached.insert(x, (next[i], i)) {
+            order.remove(&old);
+            order.insert((next[i], i));
+            stats.hits += 1;
+            continue;
+        }
+        cached.remove(x);
+        stats.misses += 1;
+        let w = weigh(x);
+        if w > n || next[i] == usize::MAX {
+            continue;
+        }
+        // 放不下时 新元素比缓存中最远的元素更远则旁路 否则淘汰最远的元素
+        while weight + w > n {
+            match order.last().copied() {
+                Some((far, j)) if far > next[i] => {
+                    order.remove(&(far, j));
+                    cached.remove(&trace[j]);
+                    weight -= weigh(&trace[j]);
+                    stats.evictions += 1;
+                }
+                _ => break,
+            }
+        }
+        if weight + w <= n {
+            cached.insert(x, (next[i], i));
+            order.insert((next[i], i));
+            weight += w;
         }
     }
+    stats
 }
\ No newline at end of file
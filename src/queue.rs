@@ -7,8 +7,8 @@ pub trait QueueCache<T> {
     fn dequeue(&mut self) -> Option<T>;
 }
 
-// 队列结构 方便直接用 linked 约束 Clone 等
-pub struct Queue<R, T> where R: QueueCache<T>, T: Clone + PartialOrd + PartialEq {
+// 队列结构 元素类型不需要额外约束
+pub struct Queue<R, T> where R: QueueCache<T> {
     size: usize,
     len: usize,
     cache: R,
@@ -16,7 +16,7 @@ pub struct Queue<R, T> where R: QueueCache<T>, T: Clone + PartialOrd + PartialEq
 }
 
 // 实现队列
-impl<R, T> Queue<R, T> where R: QueueCache<T>, T: Clone + PartialOrd + PartialEq {
+impl<R, T> Queue<R, T> where R: QueueCache<T> {
     pub fn new(n: usize) -> Self {
         Queue {
             len: 0,
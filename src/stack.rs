@@ -1,3 +1,11 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Debug;
+use std::mem;
+use std::sync::Arc;
+
 // 栈结构 容量 元素数量 底层存储 特点后进先出
 #[derive(Debug)]
 pub struct Stack<T> {
@@ -32,6 +40,13 @@ impl<T> Stack<T> {
         }
         None
     }
+    // 查看栈顶元素 不出栈
+    pub fn peek(&self) -> Option<&T> {
+        self.inner.last()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
 }
 
 // 模拟浏览器
@@ -78,6 +93,12 @@ impl<T> Browser<T> where T: Copy {
     }
 }
 
+impl<T> Default for Browser<T> where T: Copy {
+    fn default() -> Self {
+        Browser::new()
+    }
+}
+
 // 括号匹配
 pub fn brackets_match(s: &str) -> bool {
     let mut v = Stack::new(50);
@@ -117,91 +138,865 @@ pub fn brackets_match(s: &str) -> bool {
     false
 }
 
-// 计算表达式
-pub fn calculate(s: &str) -> Option<i32> {
-    let s = s.chars();
-    let mut v1 = Stack::new(20);
-    let mut v2 = Stack::new(20);
-    // 循环表达式
-    for i in s {
-        match i {
-            '+' | '-' | '*' | '/' => {
-                if v2.count == 0 {
-                    v2.push(i);
+
+// 运算符 优先级从低到高：加减 乘除取余 负号 乘方
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    // 一元负号
+    Neg,
+}
+
+impl Op {
+    // 优先级 数字越大越先计算
+    pub fn precedence(self) -> u8 {
+        match self {
+            Op::Add | Op::Sub => 1,
+            Op::Mul | Op::Div | Op::Rem => 2,
+            Op::Neg => 3,
+            Op::Pow => 4,
+        }
+    }
+    // 右结合 2 ^ 3 ^ 2 = 2 ^ (3 ^ 2)，负号是前缀同样从右往左结合
+    pub fn is_right(self) -> bool {
+        matches!(self, Op::Pow | Op::Neg)
+    }
+    pub fn symbol(self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Sub | Op::Neg => '-',
+            Op::Mul => '*',
+            Op::Div => '/',
+            Op::Rem => '%',
+            Op::Pow => '^',
+        }
+    }
+    // 计算 pos 为运算符位置，负号没有左操作数
+    fn apply<N>(self, pos: usize, a: Option<N>, b: N) -> Result<N, CalcError> where N: Number {
+        let r = match a {
+            Some(a) => N::binary(self, a, b),
+            None => b.neg(),
+        };
+        r.map_err(|e| e.at(pos))
+    }
+}
+
+// 运算错误 不带位置，求值时补上出错的运算符或者函数的位置
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArithError {
+    // 除数为 0
+    DivisionByZero,
+    // 超出数值类型的范围
+    Overflow,
+    // 操作数不在定义域内 例如整数的负数次幂
+    Domain,
+}
+
+impl ArithError {
+    fn at(self, pos: usize) -> CalcError {
+        match self {
+            ArithError::DivisionByZero => CalcError::DivisionByZero { pos },
+            ArithError::Overflow => CalcError::Overflow { pos },
+            ArithError::Domain => CalcError::Domain { pos },
+        }
+    }
+}
+
+impl fmt::Display for ArithError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArithError::DivisionByZero => write!(f, "division by zero"),
+            ArithError::Overflow => write!(f, "arithmetic overflow"),
+            ArithError::Domain => write!(f, "operand out of domain"),
+        }
+    }
+}
+
+impl Error for ArithError {}
+
+// 计算模式 数值类型负责解析字面量和运算
+// 提供 f64 浮点数、i64 整数（溢出时返回错误）和 Rational 有理数三种实现
+pub trait Number: Clone + Debug + fmt::Display + PartialOrd {
+    // 解析数字字面量（数字和小数点）
+    fn parse(s: &str) -> Result<Self, ArithError>;
+    // 二元运算
+    fn binary(op: Op, a: Self, b: Self) -> Result<Self, ArithError>;
+    fn neg(self) -> Result<Self, ArithError>;
+    // 输出为文本后相当于哪个运算符 例如负数相当于负号、分数相当于除号，用于决定是否加括号
+    fn literal_op(&self) -> Option<Op>;
+}
+
+impl Number for f64 {
+    fn parse(s: &str) -> Result<Self, ArithError> {
+        s.parse().map_err(|_| ArithError::Domain)
+    }
+    fn binary(op: Op, a: Self, b: Self) -> Result<Self, ArithError> {
+        match op {
+            Op::Add => Ok(a + b),
+            Op::Sub => Ok(a - b),
+            Op::Mul => Ok(a * b),
+            Op::Div | Op::Rem if b == 0.0 => Err(ArithError::DivisionByZero),
+            Op::Div => Ok(a / b),
+            Op::Rem => Ok(a % b),
+            Op::Pow => Ok(a.powf(b)),
+            Op::Neg => b.neg(),
+        }
+    }
+    fn neg(self) -> Result<Self, ArithError> {
+        Ok(-self)
+    }
+    fn literal_op(&self) -> Option<Op> {
+        if self.is_sign_negative() { Some(Op::Neg) } else { None }
+    }
+}
+
+// 整数 除法向 0 取整，溢出返回错误而不是回绕
+impl Number for i64 {
+    fn parse(s: &str) -> Result<Self, ArithError> {
+        if s.contains('.') {
+            return Err(ArithError::Domain);
+        }
+        // 只包含数字 解析失败说明超出范围
+        s.parse().map_err(|_| ArithError::Overflow)
+    }
+    fn binary(op: Op, a: Self, b: Self) -> Result<Self, ArithError> {
+        let r = match op {
+            Op::Add => a.checked_add(b),
+            Op::Sub => a.checked_sub(b),
+            Op::Mul => a.checked_mul(b),
+            Op::Div | Op::Rem if b == 0 => return Err(ArithError::DivisionByZero),
+            Op::Div => a.checked_div(b),
+            Op::Rem => a.checked_rem(b),
+            Op::Pow if b < 0 => return Err(ArithError::Domain),
+            Op::Pow => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+            Op::Neg => b.checked_neg(),
+        };
+        r.ok_or(ArithError::Overflow)
+    }
+    fn neg(self) -> Result<Self, ArithError> {
+        self.checked_neg().ok_or(ArithError::Overflow)
+    }
+    fn literal_op(&self) -> Option<Op> {
+        if *self < 0 { Some(Op::Neg) } else { None }
+    }
+}
+
+// 有理数 分子分母互质且分母为正，所以可以直接比较是否相等
+// 运算的中间结果用 i128 计算，约分后超出 i64 时返回溢出错误
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i64,
+    den: i64,
+}
+
+impl Rational {
+    // 分母为 0 或者约分后超出范围时返回 None
+    pub fn new(num: i64, den: i64) -> Option<Self> {
+        Rational::reduce(num as i128, den as i128).ok()
+    }
+    // 分子
+    pub fn numer(&self) -> i64 {
+        self.num
+    }
+    // 分母
+    pub fn denom(&self) -> i64 {
+        self.den
+    }
+    pub fn to_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+    // 约分并把符号放到分子上
+    fn reduce(num: i128, den: i128) -> Result<Self, ArithError> {
+        if den == 0 {
+            return Err(ArithError::DivisionByZero);
+        }
+        let g = gcd(num.unsigned_abs(), den.unsigned_abs()) as i128;
+        let (num, den) = if den < 0 { (-num / g, -den / g) } else { (num / g, den / g) };
+        match (i64::try_from(num), i64::try_from(den)) {
+            (Ok(num), Ok(den)) => Ok(Rational { num, den }),
+            _ => Err(ArithError::Overflow),
+        }
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl From<i64> for Rational {
+    fn from(n: i64) -> Self {
+        Rational { num: n, den: 1 }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.num as i128 * other.den as i128).cmp(&(other.num as i128 * self.den as i128))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+// 小数字面量精确转换 1.25 = 125 / 100 = 5 / 4
+impl Number for Rational {
+    fn parse(s: &str) -> Result<Self, ArithError> {
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        if frac.contains('.') || int.len() + frac.len() == 0 {
+            return Err(ArithError::Domain);
+        }
+        let digits = format!("{}{}", int, frac);
+        let num: i128 = digits.parse().map_err(|_| ArithError::Overflow)?;
+        let den = u32::try_from(frac.len()).ok().and_then(|n| 10i128.checked_pow(n)).ok_or(ArithError::Overflow)?;
+        Rational::reduce(num, den)
+    }
+    fn binary(op: Op, a: Self, b: Self) -> Result<Self, ArithError> {
+        let (an, ad, bn, bd) = (a.num as i128, a.den as i128, b.num as i128, b.den as i128);
+        match op {
+            Op::Add => Rational::reduce(an * bd + bn * ad, ad * bd),
+            Op::Sub => Rational::reduce(an * bd - bn * ad, ad * bd),
+            Op::Mul => Rational::reduce(an * bn, ad * bd),
+            Op::Div | Op::Rem if bn == 0 => Err(ArithError::DivisionByZero),
+            Op::Div => Rational::reduce(an * bd, ad * bn),
+            // a = x / d, b = y / d 时 a % b = (x % y) / d
+            Op::Rem => Rational::reduce((an * bd) % (bn * ad), ad * bd),
+            // 只支持整数次幂 负数次幂取倒数
+            Op::Pow if b.den != 1 => Err(ArithError::Domain),
+            Op::Pow => {
+                let e = u32::try_from(b.num.unsigned_abs()).map_err(|_| ArithError::Overflow)?;
+                let num = a.num.checked_pow(e).ok_or(ArithError::Overflow)?;
+                let den = a.den.checked_pow(e).ok_or(ArithError::Overflow)?;
+                if b.num < 0 {
+                    Rational::reduce(den as i128, num as i128)
                 } else {
-                    let y = v2.inner[v2.count - 1];
-                    if ord(i, y) {
-                        if let Some(b) = v2.pop() {
-                            if let Some(a2) = v1.pop() {
-                                if let Some(a1) = v1.pop() {
-                                    if let Some(n) = cal(a1, a2, b) {
-                                        v1.push(n);
-                                        v2.push(i);
-                                    }
-                                }
-                            }
-                        }
-                    } else {
-                        v2.push(i);
+                    Rational::reduce(num as i128, den as i128)
+                }
+            }
+            Op::Neg => b.neg(),
+        }
+    }
+    fn neg(self) -> Result<Self, ArithError> {
+        Rational::reduce(-(self.num as i128), self.den as i128)
+    }
+    fn literal_op(&self) -> Option<Op> {
+        if self.den != 1 {
+            Some(Op::Div)
+        } else if self.num < 0 {
+            Some(Op::Neg)
+        } else {
+            None
+        }
+    }
+}
+
+// 词法单元
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind<N = f64> {
+    Num(N),
+    Op(Op),
+    LParen,
+    RParen,
+    Comma,
+    // 变量
+    Var(String),
+    // 函数名和左括号 只出现在词法分析结果中
+    Func(String),
+    // 函数调用和参数个数 只出现在后缀表达式中
+    Call { name: String, argc: usize },
+}
+
+// 词法单元和它在表达式中的位置（字节下标）
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token<N = f64> {
+    pub kind: TokenKind<N>,
+    pub pos: usize,
+}
+
+// 负号输出为 neg 以便和减号区分，函数调用输出为 函数名/参数个数
+impl<N> fmt::Display for Token<N> where N: fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TokenKind::Num(n) => write!(f, "{}", n),
+            TokenKind::Op(Op::Neg) => f.write_str("neg"),
+            TokenKind::Op(op) => write!(f, "{}", op.symbol()),
+            TokenKind::LParen => f.write_str("("),
+            TokenKind::RParen => f.write_str(")"),
+            TokenKind::Comma => f.write_str(","),
+            TokenKind::Var(name) => f.write_str(name),
+            TokenKind::Func(name) => write!(f, "{}(", name),
+            TokenKind::Call { name, argc } => write!(f, "{}/{}", name, argc),
+        }
+    }
+}
+
+// 表达式错误 pos 为出错位置（字节下标）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CalcError {
+    // 无法识别或者出现在错误位置的符号
+    UnexpectedToken { pos: usize, token: String },
+    // 表达式不完整
+    UnexpectedEnd { pos: usize },
+    // 括号不匹配
+    MismatchedParen { pos: usize },
+    // 除数为 0
+    DivisionByZero { pos: usize },
+    // 超出数值类型的范围
+    Overflow { pos: usize },
+    // 操作数不在定义域内
+    Domain { pos: usize },
+    // 未定义的变量
+    UnknownVariable { pos: usize, name: String },
+    // 未定义的函数
+    UnknownFunction { pos: usize, name: String },
+    // 函数参数个数不对
+    Arity { pos: usize, name: String, argc: usize },
+}
+
+impl CalcError {
+    pub fn pos(&self) -> usize {
+        match self {
+            CalcError::UnexpectedToken { pos, .. }
+            | CalcError::UnexpectedEnd { pos }
+            | CalcError::MismatchedParen { pos }
+            | CalcError::DivisionByZero { pos }
+            | CalcError::Overflow { pos }
+            | CalcError::Domain { pos }
+            | CalcError::UnknownVariable { pos, .. }
+            | CalcError::UnknownFunction { pos, .. }
+            | CalcError::Arity { pos, .. } => *pos,
+        }
+    }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::UnexpectedToken { pos, token } => write!(f, "unexpected token {:?} at {}", token, pos),
+            CalcError::UnexpectedEnd { pos } => write!(f, "unexpected end of expression at {}", pos),
+            CalcError::MismatchedParen { pos } => write!(f, "mismatched parenthesis at {}", pos),
+            CalcError::DivisionByZero { pos } => write!(f, "division by zero at {}", pos),
+            CalcError::Overflow { pos } => write!(f, "arithmetic overflow at {}", pos),
+            CalcError::Domain { pos } => write!(f, "operand out of domain at {}", pos),
+            CalcError::UnknownVariable { pos, name } => write!(f, "unknown variable {:?} at {}", name, pos),
+            CalcError::UnknownFunction { pos, name } => write!(f, "unknown function {:?} at {}", name, pos),
+            CalcError::Arity { pos, name, argc } => write!(f, "wrong number of arguments ({}) for {:?} at {}", argc, name, pos),
+        }
+    }
+}
+
+impl Error for CalcError {}
+
+// 词法分析 浮点数模式
+pub fn tokenize(s: &str) -> Result<Vec<Token>, CalcError> {
+    tokenize_as(s)
+}
+
+// 词法分析 数字支持多位和小数，由计算模式 N 解析
+// 标识符后面紧跟左括号时是函数调用，否则是变量
+// 减号前面没有操作数（开头、运算符、左括号、逗号之后）时当作负号
+pub fn tokenize_as<N>(s: &str) -> Result<Vec<Token<N>>, CalcError> where N: Number {
+    let mut v: Vec<Token<N>> = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        let kind = match c {
+            _ if c.is_whitespace() => continue,
+            '0'..='9' | '.' => {
+                let mut end = pos + 1;
+                while let Some(&(i, d)) = chars.peek() {
+                    if !d.is_ascii_digit() && d != '.' {
+                        break;
                     }
+                    end = i + 1;
+                    chars.next();
+                }
+                let text = &s[pos..end];
+                match N::parse(text) {
+                    Ok(n) => TokenKind::Num(n),
+                    Err(ArithError::Overflow) => return Err(CalcError::Overflow { pos }),
+                    Err(_) => return Err(CalcError::UnexpectedToken { pos, token: text.to_string() }),
                 }
             }
-            _ => {
-                if let Ok(x) = i.to_string().parse() {
-                    v1.push(x);
+            _ if c.is_alphabetic() || c == '_' => {
+                let mut end = pos + c.len_utf8();
+                while let Some(&(i, d)) = chars.peek() {
+                    if !d.is_alphanumeric() && d != '_' {
+                        break;
+                    }
+                    end = i + d.len_utf8();
+                    chars.next();
+                }
+                let name = s[pos..end].to_string();
+                let mut ahead = chars.clone();
+                while ahead.next_if(|&(_, d)| d.is_whitespace()).is_some() {}
+                if ahead.next_if(|&(_, d)| d == '(').is_some() {
+                    chars = ahead;
+                    TokenKind::Func(name)
+                } else {
+                    TokenKind::Var(name)
                 }
             }
-        }
+            '-' => {
+                let operand = matches!(v.last().map(|t| &t.kind), Some(TokenKind::Num(_)) | Some(TokenKind::Var(_)) | Some(TokenKind::RParen));
+                TokenKind::Op(if operand { Op::Sub } else { Op::Neg })
+            }
+            '+' => TokenKind::Op(Op::Add),
+            '*' => TokenKind::Op(Op::Mul),
+            '/' => TokenKind::Op(Op::Div),
+            '%' => TokenKind::Op(Op::Rem),
+            '^' => TokenKind::Op(Op::Pow),
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            ',' => TokenKind::Comma,
+            _ => return Err(CalcError::UnexpectedToken { pos, token: c.to_string() }),
+        };
+        v.push(Token { kind, pos });
     }
-    // 计算剩余表达式元素
-    for _ in 0..v1.count {
-        if let Some(b) = v2.pop() {
-            if let Some(a2) = v1.pop() {
-                if let Some(a1) = v1.pop() {
-                    if let Some(n) = cal(a1, a2, b) {
-                        v1.push(n);
+    Ok(v)
+}
+
+// 计算表达式 浮点数模式，只能使用内置函数
+pub fn calculate(s: &str) -> Result<f64, CalcError> {
+    Env::new().calculate(s)
+}
+
+// 中缀表达式转换为后缀表达式 浮点数模式
+pub fn to_postfix(s: &str) -> Result<Vec<Token>, CalcError> {
+    to_postfix_as(s)
+}
+
+// 调度场算法 中缀表达式转换为后缀表达式（逆波兰表达式）
+// 数字和变量直接输出，运算符入栈前先把栈顶优先级更高（左结合时相同也算）的运算符输出
+// 遇到右括号时输出到左括号为止，最后输出剩余运算符 括号不会出现在结果中
+// 函数名和它的左括号一起入栈，遇到逗号时输出到函数为止并把参数个数加一，右括号时输出函数调用
+pub fn to_postfix_as<N>(s: &str) -> Result<Vec<Token<N>>, CalcError> where N: Number {
+    let tokens = tokenize_as::<N>(s)?;
+    let mut out = Vec::with_capacity(tokens.len());
+    // 栈容量按词法单元个数 不限制表达式长度
+    let mut ops: Stack<Token<N>> = Stack::new(tokens.len());
+    // 每层函数调用已经遇到的参数个数
+    let mut args: Stack<usize> = Stack::new(tokens.len());
+    // 下一个词法单元应该是操作数（数字、变量、函数、负号、左括号）
+    let mut operand = true;
+    // 上一个词法单元是函数 这时可以直接遇到右括号（没有参数）
+    let mut call = false;
+    for t in tokens {
+        let empty = mem::replace(&mut call, false);
+        match t.kind {
+            TokenKind::Num(_) | TokenKind::Var(_) if operand => {
+                out.push(t);
+                operand = false;
+            }
+            TokenKind::Func(_) if operand => {
+                ops.push(t);
+                args.push(1);
+                call = true;
+            }
+            TokenKind::Op(Op::Neg) | TokenKind::LParen if operand => {
+                ops.push(t);
+            }
+            TokenKind::Op(op) if !operand => {
+                while let Some(TokenKind::Op(x)) = ops.peek().map(|t| &t.kind) {
+                    if x.precedence() < op.precedence() || (x.precedence() == op.precedence() && op.is_right()) {
+                        break;
                     }
+                    out.extend(ops.pop());
                 }
+                ops.push(t);
+                operand = true;
             }
+            TokenKind::Comma if !operand => {
+                loop {
+                    match ops.peek().map(|t| &t.kind) {
+                        Some(TokenKind::Func(_)) => break,
+                        Some(TokenKind::Op(_)) => out.extend(ops.pop()),
+                        _ => return Err(unexpected(s, &t)),
+                    }
+                }
+                let n = args.pop().unwrap_or(1);
+                args.push(n + 1);
+                operand = true;
+            }
+            TokenKind::RParen if !operand || empty => {
+                loop {
+                    match ops.pop() {
+                        Some(Token { kind: TokenKind::LParen, .. }) => break,
+                        Some(Token { kind: TokenKind::Func(name), pos }) => {
+                            let n = args.pop().unwrap_or(1);
+                            let argc = if empty { 0 } else { n };
+                            out.push(Token { kind: TokenKind::Call { name, argc }, pos });
+                            break;
+                        }
+                        Some(x) => out.push(x),
+                        None => return Err(CalcError::MismatchedParen { pos: t.pos }),
+                    }
+                }
+                operand = false;
+            }
+            _ => return Err(unexpected(s, &t)),
+        }
+    }
+    if operand {
+        return Err(CalcError::UnexpectedEnd { pos: s.len() });
+    }
+    while let Some(t) = ops.pop() {
+        if matches!(t.kind, TokenKind::LParen | TokenKind::Func(_)) {
+            return Err(CalcError::MismatchedParen { pos: t.pos });
         }
+        out.push(t);
     }
-    // 返回计算结果
-    v1.pop()
+    Ok(out)
 }
 
-// 比较优先级 实际感觉用 Enum 实现符号的 Ord 和 Eq 会更好
-// 优先级低或者相同返回 true 优先级高返回 false
-fn ord(x: char, y: char) -> bool {
-    match (x, y) {
-        ('+', '-') | ('-', '+') | ('*', '/') | ('/', '*') => {
-            true
-        }
-        ('+', '*') | ('+', '/') | ('-', '*') | ('-', '/') => {
-            true
+// 计算后缀表达式 浮点数模式，只能使用内置函数
+pub fn eval_rpn(tokens: &[Token]) -> Result<f64, CalcError> {
+    Env::new().eval_rpn(tokens)
+}
+
+// 解析为语法树 浮点数模式
+pub fn parse(s: &str) -> Result<Expr, CalcError> {
+    parse_as(s)
+}
+
+// 解析为语法树 由后缀表达式归约得到
+pub fn parse_as<N>(s: &str) -> Result<Expr<N>, CalcError> where N: Number {
+    fold_rpn(&to_postfix_as(s)?, |pos, node| {
+        Ok(match node {
+            Node::Num(n) => Expr::Num(n),
+            Node::Var(name) => Expr::Var { pos, name: name.to_string() },
+            Node::Op(op, Some(a), b) => Expr::Binary { op, pos, lhs: Box::new(a), rhs: Box::new(b) },
+            Node::Op(_, None, b) => Expr::Neg { pos, expr: Box::new(b) },
+            Node::Call(name, args) => Expr::Call { pos, name: name.to_string(), args },
+        })
+    })
+}
+
+// 后缀表达式归约时的节点 运算符和函数的操作数已经归约好
+enum Node<'a, N, T> {
+    Num(N),
+    Var(&'a str),
+    // 负号的左操作数为 None
+    Op(Op, Option<T>, T),
+    Call(&'a str, Vec<T>),
+}
+
+// 按后缀表达式归约 数字入栈，遇到运算符和函数时弹出操作数交给 f 处理后把结果入栈
+// 操作数不足或者剩余多个结果时返回运算符/多余操作数位置的错误
+fn fold_rpn<'a, N, T, F>(tokens: &'a [Token<N>], mut f: F) -> Result<T, CalcError>
+where N: Clone + fmt::Display, F: FnMut(usize, Node<'a, N, T>) -> Result<T, CalcError> {
+    let mut v: Stack<(T, usize)> = Stack::new(tokens.len());
+    for t in tokens {
+        let missing = || CalcError::UnexpectedToken { pos: t.pos, token: t.to_string() };
+        let node = match &t.kind {
+            TokenKind::Num(n) => Node::Num(n.clone()),
+            TokenKind::Var(name) => Node::Var(name),
+            TokenKind::Op(op) => {
+                let (b, _) = v.pop().ok_or_else(missing)?;
+                let a = match op {
+                    Op::Neg => None,
+                    _ => Some(v.pop().ok_or_else(missing)?.0),
+                };
+                Node::Op(*op, a, b)
+            }
+            TokenKind::Call { name, argc } => {
+                let mut args = Vec::new();
+                for _ in 0..*argc {
+                    args.push(v.pop().ok_or_else(missing)?.0);
+                }
+                args.reverse();
+                Node::Call(name, args)
+            }
+            _ => return Err(missing()),
+        };
+        v.push((f(t.pos, node)?, t.pos));
+    }
+    match (v.pop(), v.pop()) {
+        (Some((x, _)), None) => Ok(x),
+        (Some((_, pos)), Some(_)) => {
+            let token = tokens.iter().find(|t| t.pos == pos).map(|t| t.to_string()).unwrap_or_default();
+            Err(CalcError::UnexpectedToken { pos, token })
         }
-        (_, _) => {
-            false
+        _ => Err(CalcError::UnexpectedEnd { pos: 0 }),
+    }
+}
+
+// 用户函数
+type Func<N> = Arc<dyn Fn(&[N]) -> Result<N, ArithError> + Send + Sync>;
+
+// 参数个数范围 [min, max]，max 为 None 时不限制
+#[derive(Clone)]
+struct Function<N> {
+    min: usize,
+    max: Option<usize>,
+    f: Func<N>,
+}
+
+// 计算环境 变量和函数，N 为计算模式（f64、i64、Rational）
+// 新建时注册可变参数函数 min max sum，同名函数和变量会被覆盖
+#[derive(Clone)]
+pub struct Env<N = f64> {
+    vars: HashMap<String, N>,
+    funcs: HashMap<String, Function<N>>,
+}
+
+impl<N> Debug for Env<N> where N: Debug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut funcs: Vec<&String> = self.funcs.keys().collect();
+        funcs.sort();
+        f.debug_struct("Env").field("vars", &self.vars).field("funcs", &funcs).finish()
+    }
+}
+
+impl<N> Default for Env<N> where N: Number {
+    fn default() -> Self {
+        Env::new()
+    }
+}
+
+impl<N> Env<N> where N: Number {
+    pub fn new() -> Self {
+        let mut env = Env {
+            vars: HashMap::new(),
+            funcs: HashMap::new(),
+        };
+        env.variadic("min", 1, |v: &[N]| v.iter().cloned().reduce(|m, x| if x < m { x } else { m }).ok_or(ArithError::Domain));
+        env.variadic("max", 1, |v: &[N]| v.iter().cloned().reduce(|m, x| if x > m { x } else { m }).ok_or(ArithError::Domain));
+        env.variadic("sum", 0, |v: &[N]| v.iter().cloned().try_fold(N::parse("0")?, |s, x| N::binary(Op::Add, s, x)));
+        env
+    }
+    // 设置变量 返回原来的值
+    pub fn set(&mut self, name: &str, n: N) -> Option<N> {
+        self.vars.insert(name.to_string(), n)
+    }
+    pub fn get(&self, name: &str) -> Option<&N> {
+        self.vars.get(name)
+    }
+    // 注册固定参数个数的函数
+    pub fn function<F>(&mut self, name: &str, argc: usize, f: F) where F: Fn(&[N]) -> Result<N, ArithError> + Send + Sync + 'static {
+        let func = Function { min: argc, max: Some(argc), f: Arc::new(f) };
+        self.funcs.insert(name.to_string(), func);
+    }
+    // 注册可变参数函数 参数个数至少为 min
+    pub fn variadic<F>(&mut self, name: &str, min: usize, f: F) where F: Fn(&[N]) -> Result<N, ArithError> + Send + Sync + 'static {
+        let func = Function { min, max: None, f: Arc::new(f) };
+        self.funcs.insert(name.to_string(), func);
+    }
+    // 计算表达式
+    pub fn calculate(&self, s: &str) -> Result<N, CalcError> {
+        self.eval_rpn(&to_postfix_as(s)?)
+    }
+    // 计算后缀表达式 同一个公式转换一次后可以在不同的环境中多次计算
+    pub fn eval_rpn(&self, tokens: &[Token<N>]) -> Result<N, CalcError> {
+        fold_rpn(tokens, |pos, node| match node {
+            Node::Num(n) => Ok(n),
+            Node::Var(name) => self.var(pos, name),
+            Node::Op(op, a, b) => op.apply(pos, a, b),
+            Node::Call(name, args) => self.call(pos, name, &args),
+        })
+    }
+    // 计算语法树 转换为后缀表达式后用栈计算，深层语法树不会栈溢出
+    pub fn eval(&self, e: &Expr<N>) -> Result<N, CalcError> {
+        self.eval_rpn(&e.to_postfix())
+    }
+    fn var(&self, pos: usize, name: &str) -> Result<N, CalcError> {
+        self.vars.get(name).cloned().ok_or_else(|| CalcError::UnknownVariable { pos, name: name.to_string() })
+    }
+    fn call(&self, pos: usize, name: &str, args: &[N]) -> Result<N, CalcError> {
+        let func = self.funcs.get(name).ok_or_else(|| CalcError::UnknownFunction { pos, name: name.to_string() })?;
+        if args.len() < func.min || func.max.is_some_and(|max| args.len() > max) {
+            return Err(CalcError::Arity { pos, name: name.to_string(), argc: args.len() });
         }
+        (func.f)(args).map_err(|e| e.at(pos))
     }
 }
 
-// 计算转换 char 为计算操作符返回结果
-fn cal(x: i32, y: i32, z: char) -> Option<i32> {
-    match z {
-        '+' => {
-            Some(x + y)
+// 语法树 pos 为变量、运算符、函数在表达式中的位置
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr<N = f64> {
+    Num(N),
+    Var { pos: usize, name: String },
+    Neg { pos: usize, expr: Box<Expr<N>> },
+    Binary { op: Op, pos: usize, lhs: Box<Expr<N>>, rhs: Box<Expr<N>> },
+    Call { pos: usize, name: String, args: Vec<Expr<N>> },
+}
+
+impl<N> Expr<N> where N: Number {
+    // 求值 只能使用内置函数
+    pub fn eval(&self) -> Result<N, CalcError> {
+        Env::new().eval(self)
+    }
+    // 转换为后缀表达式 后序遍历用显式栈代替递归，数字没有位置记为 0
+    pub fn to_postfix(&self) -> Vec<Token<N>> {
+        let mut out = Vec::new();
+        // 第二个值表示子节点是否已经输出
+        let mut stack = vec![(self, false)];
+        while let Some((e, done)) = stack.pop() {
+            let kind = match e {
+                Expr::Num(n) => TokenKind::Num(n.clone()),
+                Expr::Var { name, .. } => TokenKind::Var(name.clone()),
+                Expr::Neg { .. } if done => TokenKind::Op(Op::Neg),
+                Expr::Binary { op, .. } if done => TokenKind::Op(*op),
+                Expr::Call { name, args, .. } if done => TokenKind::Call { name: name.clone(), argc: args.len() },
+                Expr::Neg { expr, .. } => {
+                    stack.push((e, true));
+                    stack.push((expr, false));
+                    continue;
+                }
+                Expr::Binary { lhs, rhs, .. } => {
+                    stack.push((e, true));
+                    stack.push((rhs, false));
+                    stack.push((lhs, false));
+                    continue;
+                }
+                Expr::Call { args, .. } => {
+                    stack.push((e, true));
+                    stack.extend(args.iter().rev().map(|a| (a, false)));
+                    continue;
+                }
+            };
+            out.push(Token { kind, pos: e.pos() });
         }
-        '-' => {
-            Some(x - y)
+        out
+    }
+    // 在表达式中的位置 数字为 0
+    fn pos(&self) -> usize {
+        match self {
+            Expr::Num(_) => 0,
+            Expr::Var { pos, .. } | Expr::Neg { pos, .. } | Expr::Binary { pos, .. } | Expr::Call { pos, .. } => *pos,
         }
-        '*' => {
-            Some(x * y)
+    }
+    // 优先级 数字按输出形式计算，变量和函数调用最高
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Num(n) => n.literal_op().map_or(u8::MAX, Op::precedence),
+            Expr::Var { .. } | Expr::Call { .. } => u8::MAX,
+            Expr::Neg { .. } => Op::Neg.precedence(),
+            Expr::Binary { op, .. } => op.precedence(),
         }
-        '/' => {
-            Some(x / y)
+    }
+    // 以负号开头 作为右操作数时不需要括号
+    fn is_prefix(&self) -> bool {
+        match self {
+            Expr::Num(n) => n.literal_op() == Some(Op::Neg),
+            Expr::Neg { .. } => true,
+            _ => false,
         }
-        _ => {
-            None
+    }
+}
+
+// 释放时把子树移到显式栈中逐个释放，避免深层语法树递归释放导致栈溢出
+impl<N> Drop for Expr<N> {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        self.take_children(&mut stack);
+        while let Some(mut e) = stack.pop() {
+            e.take_children(&mut stack);
         }
     }
+}
+
+impl<N> Expr<N> {
+    // 取出子树放入 stack，原位置换成不持有堆内存的占位节点
+    fn take_children(&mut self, stack: &mut Vec<Expr<N>>) {
+        let leaf = || Expr::Var { pos: 0, name: String::new() };
+        match self {
+            Expr::Neg { expr, .. } => stack.push(mem::replace(&mut **expr, leaf())),
+            Expr::Binary { lhs, rhs, .. } => {
+                stack.push(mem::replace(&mut **lhs, leaf()));
+                stack.push(mem::replace(&mut **rhs, leaf()));
+            }
+            Expr::Call { args, .. } => stack.append(args),
+            Expr::Num(_) | Expr::Var { .. } => {}
+        }
+    }
+}
+
+// 输出时待处理的片段 用显式栈代替递归
+enum Piece<'a, N> {
+    // 子表达式和是否需要括号
+    Expr(&'a Expr<N>, bool),
+    Str(&'static str),
+    Op(Op),
+}
+
+// 输出最少括号的中缀表达式
+// 左操作数优先级更低，或者相同且右结合时加括号；右操作数优先级更低，或者相同且左结合时加括号
+// 负号开头的右操作数不需要括号 a ^ -b
+impl<N> fmt::Display for Expr<N> where N: Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut stack = vec![Piece::Expr(self, false)];
+        while let Some(piece) = stack.pop() {
+            let (e, paren) = match piece {
+                Piece::Expr(e, paren) => (e, paren),
+                Piece::Str(s) => {
+                    f.write_str(s)?;
+                    continue;
+                }
+                Piece::Op(op) => {
+                    write!(f, " {} ", op.symbol())?;
+                    continue;
+                }
+            };
+            // 右括号先入栈 在子节点全部输出之后才弹出
+            if paren {
+                f.write_str("(")?;
+                stack.push(Piece::Str(")"));
+            }
+            match e {
+                Expr::Num(n) => write!(f, "{}", n)?,
+                Expr::Var { name, .. } => f.write_str(name)?,
+                Expr::Neg { expr, .. } => {
+                    f.write_str("-")?;
+                    stack.push(Piece::Expr(expr, expr.precedence() < Op::Neg.precedence()));
+                }
+                Expr::Binary { op, lhs, rhs, .. } => {
+                    let p = op.precedence();
+                    stack.push(Piece::Expr(rhs, !rhs.is_prefix() && (rhs.precedence() < p || (rhs.precedence() == p && !op.is_right()))));
+                    stack.push(Piece::Op(*op));
+                    stack.push(Piece::Expr(lhs, lhs.precedence() < p || (lhs.precedence() == p && op.is_right())));
+                }
+                Expr::Call { name, args, .. } => {
+                    write!(f, "{}(", name)?;
+                    stack.push(Piece::Str(")"));
+                    for (i, a) in args.iter().enumerate().rev() {
+                        stack.push(Piece::Expr(a, false));
+                        if i > 0 {
+                            stack.push(Piece::Str(", "));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// 出现在错误位置的词法单元 取原文作为错误信息
+fn unexpected<N>(s: &str, t: &Token<N>) -> CalcError {
+    let token = match &t.kind {
+        TokenKind::Num(_) => s[t.pos..].split(|c: char| !c.is_ascii_digit() && c != '.').next().unwrap_or("").to_string(),
+        TokenKind::Var(name) | TokenKind::Func(name) => name.clone(),
+        _ => s[t.pos..].chars().next().map(|c| c.to_string()).unwrap_or_default(),
+    };
+    CalcError::UnexpectedToken { pos: t.pos, token }
 }
\ No newline at end of file